@@ -0,0 +1,97 @@
+//! Heap used by encoded patterns of large inputs, with `u32` token ids
+//! compared to the previous `usize` ids.
+//!
+//! Run with `cargo bench -p arb_comp06 --bench token_memory`.
+
+use arb_comp06::bpe::Bpe;
+use arb_comp06::chunked::{self, DEFAULT_CHUNK_SIZE};
+use arb_comp06::token::TokenId;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Counts current and peak heap usage.
+struct CountingAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(current, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+/// Runs `f`, returning its result and the peak heap used while it ran.
+fn peak_during<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let start = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(start, Ordering::Relaxed);
+    let result = f();
+    (result, PEAK.load(Ordering::Relaxed) - start)
+}
+
+/// Text-like data: words from a small vocabulary with occasional random bytes.
+fn input(len: usize, seed: u64) -> Vec<u8> {
+    let words: [&[u8]; 8] = [
+        b"alpha ", b"beta ", b"gamma ", b"delta ", b"token ", b"pair ", b"merge ", b"\n",
+    ];
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut data = Vec::with_capacity(len);
+    while data.len() < len {
+        if rng.gen_ratio(1, 16) {
+            data.push(rng.gen());
+        } else {
+            data.extend_from_slice(words[rng.gen_range(0..words.len())]);
+        }
+    }
+    data.truncate(len);
+    data
+}
+
+/// Training only sets the vocabulary, so a small sample keeps the run short.
+const TRAINING_SAMPLE_SIZE: usize = 64 << 10;
+
+fn mib(bytes: usize) -> f64 {
+    bytes as f64 / (1 << 20) as f64
+}
+
+fn main() {
+    for size in [16 << 20, 64 << 20] {
+        let data = input(size, size as u64);
+        let bpe = Bpe::new(&chunked::training_sample(
+            &[&data],
+            TRAINING_SAMPLE_SIZE,
+            TRAINING_SAMPLE_SIZE,
+        ));
+
+        let start = Instant::now();
+        let (ids, peak) = peak_during(|| chunked::encode(&bpe, &data, DEFAULT_CHUNK_SIZE));
+        let elapsed = start.elapsed();
+
+        let stored = ids.capacity() * size_of::<TokenId>();
+        // Not measured: what the same ids would take as `usize`s.
+        let stored_usize = ids.len() * size_of::<usize>();
+        println!(
+            "{:>4} MiB input: {} tokens in {elapsed:.2?}, \
+             ids {:.1} MiB (as usize ids, est.: {:.1} MiB, {:.1}x), \
+             peak while encoding {:.1} MiB",
+            size >> 20,
+            ids.len(),
+            mib(stored),
+            mib(stored_usize),
+            stored_usize as f64 / stored as f64,
+            mib(peak),
+        );
+    }
+}
@@ -1,5 +1,6 @@
-use crate::recode::{condense, expand, to_bytes, to_ids};
-use crate::token::{find_most_common_duplicate_id_pair, merge, Token, TokenId};
+use crate::interrupt::{Cancelled, Interrupt};
+use crate::recode::{condense_interruptible, expand, to_bytes, to_ids};
+use crate::token::{find_most_common_duplicate_id_pair_and_count, merge, Token, TokenId};
 use indexmap::IndexMap;
 
 pub struct Bpe {
@@ -12,6 +13,21 @@ pub struct InitInProgress {
     patterns: Vec<Vec<TokenId>>,
 }
 
+/// Statistics about one merge made by `Bpe::init_step`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeInfo {
+    pub new_id: TokenId,
+    /// Number of times the merged pair occurred in the training patterns.
+    pub pair_count: usize,
+    /// Vocabulary size after the merge.
+    pub vocabulary_size: usize,
+    /// Distinct pairs (other than the merged one) that still occur more than once:
+    /// a rough estimate of the merges remaining.
+    pub remaining_pairs: usize,
+    /// Length of each training pattern after the merge.
+    pub pattern_lengths: Vec<usize>,
+}
+
 impl Bpe {
     fn add_id(&mut self, id: TokenId, token: Token) {
         self.ids_to_tokens.insert(id, token);
@@ -30,7 +46,7 @@ impl Bpe {
         let mut bpe = Self::new_iterative(data);
 
         while bpe.init_in_progress.is_some() {
-            bpe.init_step(None::<fn(usize)>);
+            bpe.init_step(None::<fn(&MergeInfo)>);
         }
 
         bpe
@@ -43,7 +59,7 @@ impl Bpe {
             init_in_progress: None,
         };
 
-        (0..=u8::MAX).for_each(|x| bpe.add_id(TokenId(x as usize), Token::Byte(x)));
+        (0..=u8::MAX).for_each(|x| bpe.add_id(TokenId::from(x), Token::Byte(x)));
 
         let patterns = data.iter().map(|x| bpe.encode(x)).collect::<Vec<_>>();
 
@@ -51,21 +67,19 @@ impl Bpe {
         bpe
     }
 
-    pub fn init_step(&mut self, new_id_callback: Option<impl Fn(usize)>) {
+    pub fn init_step(&mut self, merge_callback: Option<impl Fn(&MergeInfo)>) {
         if let Some(mut init_in_progress) = self.init_in_progress.take() {
             let patterns = &mut init_in_progress.patterns;
 
-            if let Some(((id0, id1), _count)) = find_most_common_duplicate_id_pair(patterns.iter())
+            if let Some(((id0, id1), count, duplicates)) =
+                find_most_common_duplicate_id_pair_and_count(patterns.iter())
             {
-                let new_id = self.ids_to_tokens.len();
-                self.add_id(TokenId(new_id), Token::Merge(id0, id1));
-                if let Some(ref f) = new_id_callback {
-                    f(new_id);
-                }
+                let new_id = TokenId::from_index(self.ids_to_tokens.len());
+                self.add_id(new_id, Token::Merge(id0, id1));
 
                 let merge_if = |current_id, next_id| {
                     if current_id == id0 && next_id == id1 {
-                        Some(TokenId(new_id))
+                        Some(new_id)
                     } else {
                         None
                     }
@@ -76,16 +90,35 @@ impl Bpe {
                     .map(|pattern| merge(pattern.iter().copied(), merge_if))
                     .collect();
 
+                if let Some(ref f) = merge_callback {
+                    f(&MergeInfo {
+                        new_id,
+                        pair_count: count,
+                        vocabulary_size: self.ids_to_tokens.len(),
+                        remaining_pairs: duplicates - 1,
+                        pattern_lengths: patterns.iter().map(Vec::len).collect(),
+                    });
+                }
+
                 self.init_in_progress = Some(init_in_progress);
             }
         }
     }
 
     pub fn encode(&self, data: &[u8]) -> Vec<TokenId> {
+        self.encode_interruptible(data, Interrupt::none())
+            .expect("not cancellable")
+    }
+
+    pub fn encode_interruptible(
+        &self,
+        data: &[u8],
+        interrupt: Interrupt<'_>,
+    ) -> Result<Vec<TokenId>, Cancelled> {
         let pattern = to_ids(data, &self.tokens_to_ids);
         let merge_if = |id0, id1| self.tokens_to_ids.get(&Token::Merge(id0, id1)).copied();
 
-        condense(pattern, merge_if)
+        condense_interruptible(pattern, merge_if, interrupt)
     }
 
     pub fn decode(&self, data: Vec<TokenId>) -> Vec<u8> {
@@ -95,11 +128,26 @@ impl Bpe {
 
         to_bytes(&result, &self.ids_to_tokens)
     }
+
+    /// Depth of the merge tree of every token, indexed by `TokenId`: 0 for a
+    /// byte, and one more than the deeper child for a merge.
+    pub fn depths(&self) -> Vec<u32> {
+        let mut depths: Vec<u32> = Vec::with_capacity(self.ids_to_tokens.len());
+        for (id, token) in &self.ids_to_tokens {
+            debug_assert_eq!(id.index(), depths.len(), "ids are assigned in order");
+            depths.push(match *token {
+                Token::Byte(_) => 0,
+                Token::Merge(id0, id1) => 1 + depths[id0.index()].max(depths[id1.index()]),
+            });
+        }
+        depths
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::interrupt::CancelToken;
 
     #[test]
     fn test_bpe() {
@@ -141,4 +189,68 @@ mod tests {
             vec![1, 2, 3, 2, 3, 4]
         );
     }
+
+    #[test]
+    fn test_depths() {
+        let bpe = Bpe::new(&[&[1, 2, 3, 2, 3, 4], &[1, 2, 3, 1, 2, 3]]);
+        let depths = bpe.depths();
+        assert_eq!(depths.len(), 258);
+        assert_eq!(depths[1], 0);
+        assert_eq!(depths[256], 1, "2 + 3");
+        assert_eq!(depths[257], 2, "1 + (2 + 3)");
+    }
+
+    #[test]
+    fn test_encode_interruptible() {
+        let bpe = Bpe::new(&[&[1, 2, 3, 2, 3, 4], &[1, 2, 3, 1, 2, 3]]);
+
+        let reports = std::cell::RefCell::new(vec![]);
+        let progress = |done, total| reports.borrow_mut().push((done, total));
+        let cancel = CancelToken::new();
+        let interrupt = Interrupt::new(Some(&cancel), Some(&progress));
+
+        assert_eq!(
+            bpe.encode_interruptible(&[1, 2, 3, 2, 3, 4], interrupt),
+            Ok(vec![TokenId(257), TokenId(256), TokenId(4)])
+        );
+        // one report of the tokens merged away after each merging pass
+        assert_eq!(*reports.borrow(), vec![(2, 6), (3, 6)]);
+
+        cancel.cancel();
+        assert_eq!(
+            bpe.encode_interruptible(&[1, 2, 3, 2, 3, 4], interrupt),
+            Err(Cancelled)
+        );
+    }
+
+    #[test]
+    fn test_init_step_merge_info() {
+        let merges = std::cell::RefCell::new(vec![]);
+        let f = |info: &MergeInfo| merges.borrow_mut().push(info.clone());
+
+        let mut bpe = Bpe::new_iterative(&[&[1, 2, 3, 2, 3, 4], &[1, 2, 3, 1, 2, 3]]);
+        while bpe.init_in_progress.is_some() {
+            bpe.init_step(Some(f));
+        }
+
+        assert_eq!(
+            merges.into_inner(),
+            vec![
+                MergeInfo {
+                    new_id: TokenId(256),
+                    pair_count: 4,
+                    vocabulary_size: 257,
+                    remaining_pairs: 1,
+                    pattern_lengths: vec![4, 4],
+                },
+                MergeInfo {
+                    new_id: TokenId(257),
+                    pair_count: 3,
+                    vocabulary_size: 258,
+                    remaining_pairs: 0,
+                    pattern_lengths: vec![3, 2],
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,150 @@
+//! Bounded-memory training and encoding for large inputs
+//!
+//! `Bpe` training holds every training pattern as `TokenId`s and rescans them
+//! for each merge, so it is trained on a sample of each large input instead.
+//! Encoding works through an input one chunk at a time, so only one chunk's
+//! intermediate state is held at once. Tokens never span a chunk boundary.
+
+use crate::bpe::Bpe;
+use crate::interrupt::{Cancelled, Interrupt};
+use crate::token::TokenId;
+
+/// Default size of the chunks inputs are sampled and encoded in.
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// Default number of bytes of each input used for training.
+pub const DEFAULT_SAMPLE_SIZE: usize = 4 << 20;
+
+/// Up to `sample_size` bytes of `data`, as chunks of `chunk_size` spread
+/// evenly across it. Returns all of `data` if it fits.
+pub fn sample(data: &[u8], chunk_size: usize, sample_size: usize) -> Vec<&[u8]> {
+    if data.len() <= sample_size {
+        return vec![data];
+    }
+
+    let chunk_size = chunk_size.clamp(1, sample_size.max(1));
+    let chunks = (sample_size / chunk_size).max(1);
+    let stride = data.len() / chunks;
+    (0..chunks)
+        .map(|i| &data[i * stride..i * stride + chunk_size])
+        .collect()
+}
+
+/// Training patterns for `Bpe::new` or `Bpe::new_iterative`: a sample of up
+/// to `sample_size` bytes of each input.
+pub fn training_sample<'a>(
+    inputs: &[&'a [u8]],
+    chunk_size: usize,
+    sample_size: usize,
+) -> Vec<&'a [u8]> {
+    inputs
+        .iter()
+        .flat_map(|data| sample(data, chunk_size, sample_size))
+        .collect()
+}
+
+/// Index in `inputs` of the input each pattern of their `training_sample`
+/// was taken from.
+pub fn sample_owners(inputs: &[&[u8]], chunk_size: usize, sample_size: usize) -> Vec<usize> {
+    inputs
+        .iter()
+        .enumerate()
+        .flat_map(|(i, data)| vec![i; sample(data, chunk_size, sample_size).len()])
+        .collect()
+}
+
+/// Total of `lengths`, one per pattern of a `training_sample`, for each of
+/// its `inputs` inputs, given the `owners` of the patterns from
+/// `sample_owners`.
+pub fn lengths_per_input(lengths: &[usize], owners: &[usize], inputs: usize) -> Vec<usize> {
+    let mut totals = vec![0; inputs];
+    for (&length, &owner) in lengths.iter().zip(owners) {
+        totals[owner] += length;
+    }
+    totals
+}
+
+/// Encodes `data` one `chunk_size` chunk at a time.
+pub fn encode(bpe: &Bpe, data: &[u8], chunk_size: usize) -> Vec<TokenId> {
+    encode_interruptible(bpe, data, chunk_size, Interrupt::none()).expect("not cancellable")
+}
+
+/// `encode`, reporting the bytes encoded before each chunk.
+pub fn encode_interruptible(
+    bpe: &Bpe,
+    data: &[u8],
+    chunk_size: usize,
+    interrupt: Interrupt<'_>,
+) -> Result<Vec<TokenId>, Cancelled> {
+    let chunk_size = chunk_size.max(1);
+    let mut ids = vec![];
+    for (i, chunk) in data.chunks(chunk_size).enumerate() {
+        interrupt.check_now(i * chunk_size, data.len())?;
+        ids.extend(bpe.encode_interruptible(chunk, interrupt.without_progress())?);
+    }
+    ids.shrink_to_fit();
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interrupt::CancelToken;
+
+    #[test]
+    fn test_sample() {
+        let data: Vec<u8> = (0..100).collect();
+        assert_eq!(sample(&data, 10, 100), [&data[..]]);
+        assert_eq!(
+            sample(&data, 10, 30),
+            [&data[0..10], &data[33..43], &data[66..76]]
+        );
+        assert_eq!(sample(&data, 50, 20), [&data[0..20]]);
+
+        assert_eq!(
+            training_sample(&[&data[..10], &data], 10, 20),
+            [&data[..10], &data[0..10], &data[50..60]]
+        );
+        let owners = sample_owners(&[&data[..10], &data], 10, 20);
+        assert_eq!(owners, [0, 1, 1]);
+        assert_eq!(lengths_per_input(&[4, 3, 5], &owners, 2), [4, 8]);
+        assert_eq!(lengths_per_input(&[], &[], 2), [0, 0]);
+    }
+
+    #[test]
+    fn test_encode() {
+        let data = b"abcabcabcabc";
+        let bpe = Bpe::new(&[data]);
+
+        // Chunks are encoded separately, so tokens never cross chunk boundaries.
+        assert_eq!(encode(&bpe, data, data.len()), bpe.encode(data));
+        let chunked: Vec<TokenId> = data.chunks(5).flat_map(|chunk| bpe.encode(chunk)).collect();
+        assert_eq!(encode(&bpe, data, 5), chunked);
+
+        let decoded = bpe.decode(encode(&bpe, data, 5));
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_encode_interruptible() {
+        let data = b"abcabcabcabc";
+        let bpe = Bpe::new(&[data]);
+
+        let reports = std::cell::RefCell::new(vec![]);
+        let progress = |done, total| reports.borrow_mut().push((done, total));
+        let cancel = CancelToken::new();
+        let interrupt = Interrupt::new(Some(&cancel), Some(&progress));
+
+        assert_eq!(
+            encode_interruptible(&bpe, data, 5, interrupt),
+            Ok(encode(&bpe, data, 5))
+        );
+        assert_eq!(*reports.borrow(), [(0, 12), (5, 12), (10, 12)]);
+
+        cancel.cancel();
+        assert_eq!(
+            encode_interruptible(&bpe, data, 5, interrupt),
+            Err(Cancelled)
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Hierarchical clustering of inputs by pairwise distance
+//!
+//! Agglomerative: every input starts as its own cluster, and the two closest
+//! clusters are merged until one is left. The merges form a `Dendrogram`.
+
+use crate::similarity::{Metric, Similarity};
+use serde_json::{json, Value};
+
+/// How the distance between two clusters is computed from the distances of
+/// their inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linkage {
+    /// The distance of the closest pair.
+    Single,
+    /// The distance of the farthest pair.
+    Complete,
+    /// The mean distance of all pairs (UPGMA).
+    Average,
+}
+
+/// The merges of a hierarchical clustering.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dendrogram {
+    /// An input, by index.
+    Leaf(usize),
+    /// Two clusters merged at `distance`.
+    Merge {
+        distance: f64,
+        left: Box<Dendrogram>,
+        right: Box<Dendrogram>,
+    },
+}
+
+impl Dendrogram {
+    /// Indices of the inputs in this cluster, left to right.
+    pub fn leaves(&self) -> Vec<usize> {
+        match self {
+            Dendrogram::Leaf(index) => vec![*index],
+            Dendrogram::Merge { left, right, .. } => {
+                let mut leaves = left.leaves();
+                leaves.extend(right.leaves());
+                leaves
+            }
+        }
+    }
+
+    /// Draws the tree, one line per cluster, with merges labelled by their
+    /// distance and inputs by their name.
+    pub fn to_text(&self, names: &[&str]) -> String {
+        let mut text = String::new();
+        self.write_text(names, "", "", &mut text);
+        text
+    }
+
+    fn write_text(&self, names: &[&str], first: &str, rest: &str, text: &mut String) {
+        match self {
+            Dendrogram::Leaf(index) => {
+                text.push_str(&format!("{first}{}\n", names[*index]));
+            }
+            Dendrogram::Merge {
+                distance,
+                left,
+                right,
+            } => {
+                text.push_str(&format!("{first}[{distance:.3}]\n"));
+                left.write_text(names, &format!("{rest}├── "), &format!("{rest}│   "), text);
+                right.write_text(names, &format!("{rest}└── "), &format!("{rest}    "), text);
+            }
+        }
+    }
+
+    /// The tree as nested JSON objects: `{"index", "name"}` for inputs and
+    /// `{"distance", "size", "children"}` for merges.
+    pub fn to_json(&self, names: &[&str]) -> Value {
+        match self {
+            Dendrogram::Leaf(index) => json!({ "index": index, "name": names[*index] }),
+            Dendrogram::Merge {
+                distance,
+                left,
+                right,
+            } => json!({
+                "distance": distance,
+                "size": self.leaves().len(),
+                "children": [left.to_json(names), right.to_json(names)],
+            }),
+        }
+    }
+}
+
+/// Distances by `metric` of every pair of inputs scored by `n_way::pairwise`.
+pub fn distance_matrix(similarities: &[Vec<Similarity>], metric: Metric) -> Vec<Vec<f64>> {
+    similarities
+        .iter()
+        .map(|row| row.iter().map(|scores| metric.distance(scores)).collect())
+        .collect()
+}
+
+/// Clusters inputs by their symmetric matrix of pairwise `distances`.
+/// Returns None if there are no inputs.
+///
+/// Of pairs at the same distance, the pair of the lowest-numbered clusters
+/// merges first, so the result is deterministic.
+pub fn cluster(distances: &[Vec<f64>], linkage: Linkage) -> Option<Dendrogram> {
+    let n = distances.len();
+    let mut distances = distances.to_vec();
+    // Indexed like `distances`. Merged clusters take the lower index.
+    let mut clusters: Vec<Option<(Dendrogram, usize)>> =
+        (0..n).map(|i| Some((Dendrogram::Leaf(i), 1))).collect();
+
+    for _ in 1..n {
+        let mut closest: Option<(usize, usize)> = None;
+        for i in 0..n {
+            for j in i + 1..n {
+                if clusters[i].is_none() || clusters[j].is_none() {
+                    continue;
+                }
+                if closest.map_or(true, |(ci, cj)| distances[i][j] < distances[ci][cj]) {
+                    closest = Some((i, j));
+                }
+            }
+        }
+        let (i, j) = closest.expect("at least two clusters are left");
+
+        let (left, size_i) = clusters[i].take().expect("cluster i is active");
+        let (right, size_j) = clusters[j].take().expect("cluster j is active");
+        let distance = distances[i][j];
+
+        // Lance-Williams update of the distances to the merged cluster.
+        for k in 0..n {
+            if clusters[k].is_none() {
+                continue;
+            }
+            let (to_i, to_j) = (distances[i][k], distances[j][k]);
+            let merged = match linkage {
+                Linkage::Single => to_i.min(to_j),
+                Linkage::Complete => to_i.max(to_j),
+                Linkage::Average => {
+                    (to_i * size_i as f64 + to_j * size_j as f64) / (size_i + size_j) as f64
+                }
+            };
+            distances[i][k] = merged;
+            distances[k][i] = merged;
+        }
+
+        let merged = Dendrogram::Merge {
+            distance,
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+        clusters[i] = Some((merged, size_i + size_j));
+    }
+
+    clusters.into_iter().flatten().next().map(|(tree, _)| tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge(distance: f64, left: Dendrogram, right: Dendrogram) -> Dendrogram {
+        Dendrogram::Merge {
+            distance,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    /// Inputs 0 and 2 are close, 1 and 3 are close, and the pairs are far apart.
+    fn two_groups() -> Vec<Vec<f64>> {
+        vec![
+            vec![0.0, 0.875, 0.125, 0.75],
+            vec![0.875, 0.0, 0.625, 0.25],
+            vec![0.125, 0.625, 0.0, 0.5],
+            vec![0.75, 0.25, 0.5, 0.0],
+        ]
+    }
+
+    #[test]
+    fn test_cluster() {
+        use Dendrogram::Leaf;
+
+        let pairs = |top| {
+            merge(
+                top,
+                merge(0.125, Leaf(0), Leaf(2)),
+                merge(0.25, Leaf(1), Leaf(3)),
+            )
+        };
+        assert_eq!(cluster(&two_groups(), Linkage::Single), Some(pairs(0.5)));
+        assert_eq!(
+            cluster(&two_groups(), Linkage::Complete),
+            Some(pairs(0.875))
+        );
+        assert_eq!(
+            cluster(&two_groups(), Linkage::Average),
+            Some(pairs((0.875 + 0.75 + 0.625 + 0.5) / 4.0))
+        );
+
+        assert_eq!(cluster(&[], Linkage::Single), None);
+        assert_eq!(cluster(&[vec![0.0]], Linkage::Single), Some(Leaf(0)));
+
+        // Ties merge the lowest-numbered clusters first.
+        let equal = vec![
+            vec![0.0, 0.5, 0.5],
+            vec![0.5, 0.0, 0.5],
+            vec![0.5, 0.5, 0.0],
+        ];
+        assert_eq!(
+            cluster(&equal, Linkage::Single),
+            Some(merge(0.5, merge(0.5, Leaf(0), Leaf(1)), Leaf(2)))
+        );
+    }
+
+    #[test]
+    fn test_dendrogram_output() {
+        let tree = cluster(&two_groups(), Linkage::Single).unwrap();
+        let names = ["a", "b", "c", "d"];
+        assert_eq!(tree.leaves(), [0, 2, 1, 3]);
+        assert_eq!(
+            tree.to_text(&names).lines().collect::<Vec<_>>(),
+            [
+                "[0.500]",
+                "├── [0.125]",
+                "│   ├── a",
+                "│   └── c",
+                "└── [0.250]",
+                "    ├── b",
+                "    └── d",
+            ]
+        );
+
+        let json = tree.to_json(&names);
+        assert_eq!(json["distance"], 0.5);
+        assert_eq!(json["size"], 4);
+        assert_eq!(json["children"][0]["children"][1]["name"], "c");
+        assert_eq!(json["children"][1]["children"][0]["index"], 1);
+    }
+}
@@ -1,4 +1,4 @@
-pub use crate::test_utils::HexCell;
+pub use crate::test_utils::{HexCell, Provenance};
 
 pub fn get_diffs(
     a: &[u8],
@@ -32,19 +32,23 @@ pub fn get_diffs(
                     a_diff.push(HexCell::Same {
                         value: *a,
                         source_id: 0,
+                        provenance: Provenance::BYTE,
                     });
                     b_diff.push(HexCell::Same {
                         value: *b,
                         source_id: 0,
+                        provenance: Provenance::BYTE,
                     });
                 } else {
                     a_diff.push(HexCell::Diff {
                         value: *a,
                         source_id: 0,
+                        provenance: Provenance::BYTE,
                     });
                     b_diff.push(HexCell::Diff {
                         value: *b,
                         source_id: 0,
+                        provenance: Provenance::BYTE,
                     });
                 }
             }
@@ -62,3 +66,144 @@ pub fn get_diffs(
     }
     (a_diff, b_diff)
 }
+
+/// Three-way diff by index of `a` and `b` against `base`. Bytes changed
+/// differently in `a` and `b` are conflicts, and missing bytes are blank.
+pub fn get_diffs3(
+    base: &[u8],
+    a: &[u8],
+    b: &[u8],
+    range: std::ops::Range<usize>,
+) -> (Vec<HexCell>, Vec<HexCell>, Vec<HexCell>) {
+    #[derive(Clone, Copy)]
+    enum Change {
+        Kept,
+        Changed,
+        Conflict,
+    }
+
+    fn cell(value: Option<&u8>, change: Change) -> HexCell {
+        let Some(&value) = value else {
+            return HexCell::Blank;
+        };
+        let source_id = 0;
+        let provenance = Provenance::BYTE;
+        match change {
+            Change::Kept => HexCell::Same {
+                value,
+                source_id,
+                provenance,
+            },
+            Change::Changed => HexCell::Diff {
+                value,
+                source_id,
+                provenance,
+            },
+            Change::Conflict => HexCell::Conflict {
+                value,
+                source_id,
+                provenance,
+            },
+        }
+    }
+
+    let mut base_diff = Vec::new();
+    let mut a_diff = Vec::new();
+    let mut b_diff = Vec::new();
+
+    for i in range {
+        let (o, x, y) = (base.get(i), a.get(i), b.get(i));
+
+        use Change::*;
+        let changes = match (x != o, y != o) {
+            (false, false) => [Kept, Kept, Kept],
+            (true, false) => [Changed, Changed, Kept],
+            (false, true) => [Changed, Kept, Changed],
+            (true, true) if x == y => [Changed, Changed, Changed],
+            (true, true) => [Conflict, Conflict, Conflict],
+        };
+
+        base_diff.push(cell(o, changes[0]));
+        a_diff.push(cell(x, changes[1]));
+        b_diff.push(cell(y, changes[2]));
+    }
+    (base_diff, a_diff, b_diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_diffs3() {
+        let same = |value| HexCell::Same {
+            value,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        };
+        let diff = |value| HexCell::Diff {
+            value,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        };
+        let conflict = |value| HexCell::Conflict {
+            value,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        };
+
+        let (base, a, b) = get_diffs3(b"abcde", b"aXcYe", b"abZYQ!", 0..6);
+        assert_eq!(
+            base,
+            [
+                same(b'a'),
+                diff(b'b'),
+                diff(b'c'),
+                diff(b'd'),
+                diff(b'e'),
+                HexCell::Blank
+            ]
+        );
+        assert_eq!(
+            a,
+            [
+                same(b'a'),
+                diff(b'X'),
+                same(b'c'),
+                diff(b'Y'),
+                same(b'e'),
+                HexCell::Blank
+            ]
+        );
+        assert_eq!(
+            b,
+            [
+                same(b'a'),
+                same(b'b'),
+                diff(b'Z'),
+                diff(b'Y'),
+                diff(b'Q'),
+                diff(b'!')
+            ]
+        );
+
+        let (base, a, b) = get_diffs3(b"a", b"b", b"c", 1..2);
+        assert_eq!(
+            (base, a, b),
+            (
+                vec![HexCell::Blank],
+                vec![HexCell::Blank],
+                vec![HexCell::Blank]
+            )
+        );
+        let (base, a, b) = get_diffs3(b"a", b"b", b"c", 0..1);
+        assert_eq!(
+            (base, a, b),
+            (
+                vec![conflict(b'a')],
+                vec![conflict(b'b')],
+                vec![conflict(b'c')]
+            )
+        );
+    }
+}
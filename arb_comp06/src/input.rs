@@ -0,0 +1,116 @@
+//! Input data, memory-mapped from files where possible
+
+use memmap2::Mmap;
+use std::fs::File;
+use std::ops::Deref;
+use std::path::Path;
+
+/// The bytes of one input, either mapped from a file or held in memory.
+///
+/// Mapped inputs are paged in by the OS as they are read, so large files
+/// don't need to fit in RAM.
+#[derive(Debug)]
+pub enum Input {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+/// Files at least this large are mapped by `Input::open`, smaller ones are read.
+pub const MAP_THRESHOLD: u64 = 64 << 20;
+
+impl Input {
+    /// Reads the file at `path` into memory.
+    pub fn read(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        std::fs::read(path).map(Self::Owned)
+    }
+
+    /// Maps the file at `path` if it is at least `MAP_THRESHOLD` bytes,
+    /// otherwise reads it.
+    ///
+    /// # Safety
+    ///
+    /// As for `Input::map`.
+    pub unsafe fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        if std::fs::metadata(&path)?.len() >= MAP_THRESHOLD {
+            Self::map(path)
+        } else {
+            Self::read(path)
+        }
+    }
+
+    /// Maps the file at `path`, falling back to reading it if it can't be mapped.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the `Input` is alive:
+    /// the mapped bytes would change underneath any borrow of them, and
+    /// reading past the end of a truncated file crashes the process.
+    pub unsafe fn map(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = File::open(&path)?;
+        // Empty files can't be mapped on every platform.
+        if file.metadata()?.len() == 0 {
+            return Ok(Self::Owned(vec![]));
+        }
+        // SAFETY: upheld by the caller; the mapping is only ever read.
+        match unsafe { Mmap::map(&file) } {
+            Ok(mmap) => Ok(Self::Mapped(mmap)),
+            Err(_) => std::fs::read(path).map(Self::Owned),
+        }
+    }
+}
+
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mapped(mmap) => mmap,
+            Self::Owned(bytes) => bytes,
+        }
+    }
+}
+
+impl AsRef<[u8]> for Input {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl From<Vec<u8>> for Input {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Owned(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_and_map() {
+        let dir = std::env::temp_dir().join(format!("arb_comp06_input_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("input.bin");
+
+        std::fs::write(&path, b"abc").unwrap();
+        // SAFETY (here and below): nothing writes the file while it is mapped.
+        let input = unsafe { Input::open(&path) }.unwrap();
+        assert!(matches!(input, Input::Owned(_)), "small files are read");
+        assert_eq!(&*input, b"abc");
+        assert_eq!(&*Input::read(&path).unwrap(), b"abc");
+
+        let input = unsafe { Input::map(&path) }.unwrap();
+        assert!(matches!(input, Input::Mapped(_)));
+        assert_eq!(&*input, b"abc");
+        drop(input);
+
+        std::fs::write(&path, b"").unwrap();
+        assert_eq!(&*unsafe { Input::map(&path) }.unwrap(), b"");
+
+        assert!(unsafe { Input::open(dir.join("missing.bin")) }.is_err());
+        assert!(Input::read(dir.join("missing.bin")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(&*Input::from(vec![1, 2]), [1, 2]);
+    }
+}
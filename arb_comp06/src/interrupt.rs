@@ -0,0 +1,115 @@
+//! Cancellation and progress reporting for long-running operations
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A cancellation flag shared between the caller and a running operation.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+/// Returned by an operation stopped by its `CancelToken`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+/// Number of loop steps between cancellation checks and progress reports.
+const CHECK_INTERVAL: usize = 1 << 16;
+
+/// Cancellation check and progress callback passed to `*_interruptible` operations.
+///
+/// The progress callback receives `(done, total)` for the current loop.
+#[derive(Clone, Copy, Default)]
+pub struct Interrupt<'a> {
+    cancel: Option<&'a CancelToken>,
+    progress: Option<&'a dyn Fn(usize, usize)>,
+}
+
+impl<'a> Interrupt<'a> {
+    /// Never cancels and reports nothing.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    pub fn new(
+        cancel: Option<&'a CancelToken>,
+        progress: Option<&'a dyn Fn(usize, usize)>,
+    ) -> Self {
+        Self { cancel, progress }
+    }
+
+    /// The same cancellation, without progress reports: for inner loops of
+    /// an operation that reports its own progress.
+    pub fn without_progress(self) -> Self {
+        Self {
+            cancel: self.cancel,
+            progress: None,
+        }
+    }
+
+    /// Called at every `step` of a loop: every `CHECK_INTERVAL` steps, reports
+    /// progress and returns `Err(Cancelled)` if cancellation was requested.
+    pub(crate) fn check(&self, step: usize, done: usize, total: usize) -> Result<(), Cancelled> {
+        if step % CHECK_INTERVAL == 0 {
+            self.check_now(done, total)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns `Err(Cancelled)` if cancellation was requested, without reporting progress.
+    pub(crate) fn cancelled(&self) -> Result<(), Cancelled> {
+        match self.cancel {
+            Some(cancel) if cancel.is_cancelled() => Err(Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Like `check`, but always checks and reports.
+    pub(crate) fn check_now(&self, done: usize, total: usize) -> Result<(), Cancelled> {
+        if let Some(progress) = self.progress {
+            progress(done, total);
+        }
+        self.cancelled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_check() {
+        let reports = RefCell::new(vec![]);
+        let progress = |done, total| reports.borrow_mut().push((done, total));
+        let cancel = CancelToken::new();
+        let interrupt = Interrupt::new(Some(&cancel), Some(&progress));
+
+        for i in 0..=CHECK_INTERVAL {
+            assert_eq!(interrupt.check(i, i / 2, 100), Ok(()));
+        }
+        assert_eq!(*reports.borrow(), vec![(0, 100), (CHECK_INTERVAL / 2, 100)]);
+
+        cancel.cancel();
+        assert_eq!(interrupt.check(1, 1, 100), Ok(()));
+        assert_eq!(interrupt.check(0, 1, 100), Err(Cancelled));
+        assert_eq!(interrupt.check_now(1, 100), Err(Cancelled));
+
+        assert_eq!(Interrupt::none().check_now(0, 0), Ok(()));
+    }
+}
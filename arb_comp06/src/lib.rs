@@ -1,10 +1,17 @@
 pub mod bpe;
+pub mod chunked;
+pub mod clustering;
 pub mod diff;
+pub mod input;
+pub mod interrupt;
 pub mod matcher;
+pub mod n_way;
 mod pairs;
 pub mod re_pair;
 pub mod recode;
-mod techniques;
+pub mod similarity;
+pub mod techniques;
 pub mod test_utils;
-mod token;
+pub mod three_way;
+pub mod token;
 mod utils;
@@ -1,37 +1,174 @@
 pub mod bpe;
+pub mod chunked;
+pub mod clustering;
+pub mod input;
+pub mod interrupt;
 pub mod matcher;
+pub mod n_way;
 mod pairs;
 pub mod re_pair;
 pub mod recode;
-mod techniques;
+pub mod similarity;
+pub mod techniques;
 pub mod test_utils;
-mod token;
+pub mod three_way;
+pub mod token;
 mod utils;
 
-use bpe::Bpe;
+use clustering::Linkage;
+use input::Input;
 use matcher::greedy00;
-use test_utils::print_ui_01;
+use similarity::Metric;
+use test_utils::{print_ui_01, print_ui_03};
+use three_way::greedy00_three_way;
 
 fn main() {
-    println!("diff test");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("matrix") => matrix(&args[1..]),
+        Some("stats") => stats(&args[1..]),
+        Some("cluster") => cluster(&args[1..]),
+        _ => diff(&args),
+    }
+}
 
-    //read files from first 2 arguments
-    let args: Vec<String> = std::env::args().collect();
-    let files = args
+/// Opens `files`, mapping large ones.
+fn open_all(files: &[String]) -> Vec<Input> {
+    files
         .iter()
-        .skip(1)
-        .take(2)
-        .map(|s| s.to_string())
-        .collect::<Vec<_>>();
-    let file1 = std::fs::read(&files[0]).expect("Could not read file");
-    let file2 = std::fs::read(&files[1]).expect("Could not read file");
+        // SAFETY: a run reads its files once and exits; like any tool reading
+        // its input, it expects the files not to be rewritten meanwhile.
+        .map(|file| unsafe { Input::open(file) }.expect("Could not read file"))
+        .collect()
+}
+
+/// Diffs the first 2 files, or base, A and B from the first 3.
+fn diff(files: &[String]) {
+    println!("diff test");
+
+    let inputs = open_all(&files[..files.len().min(3)]);
+    assert!(inputs.len() >= 2, "Expected 2 or 3 files");
+    let data = inputs.iter().map(|input| &input[..]).collect::<Vec<_>>();
+
+    // Large files are trained on a sample and encoded chunk by chunk.
+    let (bpe, ids) = n_way::encode_all(&data);
+    let decode = |x: &Vec<_>| bpe.decode(x.clone());
+
+    if let [base, a, b] = &ids[..] {
+        let regions = greedy00_three_way(base, a, b);
+        print_ui_03(&regions, decode);
+    } else {
+        let matches = greedy00(&ids[0], &ids[1]);
+        print_ui_01(&matches, decode, false);
+    }
+}
 
-    let bpe = Bpe::new(&[&file1, &file2]);
+/// Prints the encoded sizes and similarity scores of 2 files.
+fn stats(files: &[String]) {
+    let inputs = open_all(files);
+    assert_eq!(inputs.len(), 2, "Expected 2 files");
+    let data = inputs.iter().map(|input| &input[..]).collect::<Vec<_>>();
+    let (bpe, ids) = n_way::encode_all(&data);
+    let matches = greedy00(&ids[0], &ids[1]);
+    let scores = similarity::similarity(&matches, &n_way::token_lengths(&bpe));
+
+    println!("vocabulary:    {} tokens", bpe.ids_to_tokens().len());
+    for (file, (data, ids)) in files.iter().zip(data.iter().zip(&ids)) {
+        println!("{file}: {} bytes, {} tokens", data.len(), ids.len());
+    }
+    println!("bytes matched: {:.1}%", scores.same_fraction * 100.0);
+    println!("token jaccard: {:.3}", scores.jaccard);
+    println!("ncd:           {:.3}", scores.ncd);
+}
+
+/// Prints the pairwise similarity of any number of files, encoded with one
+/// vocabulary trained on all of them.
+fn matrix(files: &[String]) {
+    let inputs = open_all(files);
+    let data = inputs.iter().map(|input| &input[..]).collect::<Vec<_>>();
+    let (bpe, ids) = n_way::encode_all(&data);
+    let matrix = n_way::similarity_matrix(&ids, &n_way::token_lengths(&bpe));
+
+    println!(
+        "% of bytes matched, vocabulary of {} tokens",
+        bpe.ids_to_tokens().len()
+    );
+    print!("{:>4}", "");
+    for j in 0..files.len() {
+        print!(" {j:>5}");
+    }
+    println!();
+    for (i, row) in matrix.iter().enumerate() {
+        print!("{i:>4}");
+        for score in row {
+            print!(" {:>5.1}", score * 100.0);
+        }
+        println!("  {}", files[i]);
+    }
+}
+
+const CLUSTER_USAGE: &str = "usage: cluster [--linkage single|complete|average] \
+                             [--metric same|jaccard|ncd] [--json] FILE...";
+
+/// Prints `message` and the usage of `cluster`, and exits with status 2.
+fn cluster_usage_error(message: &str) -> ! {
+    eprintln!("{message}\n{CLUSTER_USAGE}");
+    std::process::exit(2);
+}
 
-    let ids0 = bpe.encode(&file1);
-    let ids1 = bpe.encode(&file2);
+/// Clusters any number of files by the distance of their encodings with one
+/// vocabulary, and prints the dendrogram.
+///
+/// Options before the files: `--linkage single|complete|average` (default
+/// average), `--metric same|jaccard|ncd` (default same) and `--json`.
+fn cluster(args: &[String]) {
+    let mut linkage = Linkage::Average;
+    let mut metric = Metric::SameFraction;
+    let mut json = false;
+    let mut args = args.iter();
+    let mut files = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--linkage" => {
+                linkage = match args.next().map(String::as_str) {
+                    Some("single") => Linkage::Single,
+                    Some("complete") => Linkage::Complete,
+                    Some("average") => Linkage::Average,
+                    other => cluster_usage_error(&format!("Unknown linkage {other:?}")),
+                }
+            }
+            "--metric" => {
+                metric = match args.next().map(String::as_str) {
+                    Some("same") => Metric::SameFraction,
+                    Some("jaccard") => Metric::Jaccard,
+                    Some("ncd") => Metric::Ncd,
+                    other => cluster_usage_error(&format!("Unknown metric {other:?}")),
+                }
+            }
+            "--json" => json = true,
+            option if option.starts_with("--") => {
+                cluster_usage_error(&format!("Unknown option {option:?}"))
+            }
+            _ => files.push(arg.clone()),
+        }
+    }
+    if files.is_empty() {
+        cluster_usage_error("No files to cluster");
+    }
 
-    let matches = greedy00(&ids0, &ids1);
+    let inputs = open_all(&files);
+    let data = inputs.iter().map(|input| &input[..]).collect::<Vec<_>>();
+    let (bpe, ids) = n_way::encode_all(&data);
+    let similarities = n_way::pairwise(&ids, &n_way::token_lengths(&bpe));
+    let distances = clustering::distance_matrix(&similarities, metric);
+    let Some(tree) = clustering::cluster(&distances, linkage) else {
+        return;
+    };
 
-    print_ui_01(&matches, |x| bpe.decode(x.clone()), false);
+    let names = files.iter().map(String::as_str).collect::<Vec<_>>();
+    if json {
+        println!("{:#}", tree.to_json(&names));
+    } else {
+        print!("{}", tree.to_text(&names));
+    }
 }
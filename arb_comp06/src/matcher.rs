@@ -1,3 +1,4 @@
+use crate::interrupt::{Cancelled, Interrupt};
 use crate::token::TokenId;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -20,16 +21,19 @@ fn next_match_point(
     start_index1: usize,
     ids0: &[TokenId],
     ids1: &[TokenId],
-) -> Option<(usize, usize)> {
+    interrupt: Interrupt<'_>,
+) -> Result<Option<(usize, usize)>, Cancelled> {
     let mut index0 = start_index0;
     let mut index1 = start_index1;
 
     loop {
+        interrupt.cancelled()?;
+
         if index0 >= ids0.len() {
-            return None;
+            return Ok(None);
         }
         if index1 >= ids1.len() {
-            return None;
+            return Ok(None);
         }
 
         let next0 = find_next(ids1[index1], index0, ids0);
@@ -37,16 +41,16 @@ fn next_match_point(
         match (next0, next1) {
             (Some(next0), Some(next1)) => {
                 if next0 <= next1 {
-                    return Some((next0, index1));
+                    return Ok(Some((next0, index1)));
                 } else {
-                    return Some((index0, next1));
+                    return Ok(Some((index0, next1)));
                 }
             }
             (Some(next0), None) => {
-                return Some((next0, index1));
+                return Ok(Some((next0, index1)));
             }
             (None, Some(next1)) => {
-                return Some((index0, next1));
+                return Ok(Some((index0, next1)));
             }
             (None, None) => {
                 index0 += 1;
@@ -92,12 +96,25 @@ fn consume_to_same(
 }
 
 pub fn greedy00(ids0: &[TokenId], ids1: &[TokenId]) -> Vec<Matched> {
+    greedy00_interruptible(ids0, ids1, Interrupt::none()).expect("not cancellable")
+}
+
+/// `greedy00`, reporting progress through `ids0`.
+pub fn greedy00_interruptible(
+    ids0: &[TokenId],
+    ids1: &[TokenId],
+    interrupt: Interrupt<'_>,
+) -> Result<Vec<Matched>, Cancelled> {
     let mut matches = vec![];
 
     let mut index0 = 0;
     let mut index1 = 0;
+    let mut step = 0;
+
+    while let Some((next0, next1)) = next_match_point(index0, index1, ids0, ids1, interrupt)? {
+        interrupt.check(step, index0, ids0.len())?;
+        step += 1;
 
-    while let Some((next0, next1)) = next_match_point(index0, index1, ids0, ids1) {
         if let Some(m) = consume_to_diff(ids0, index0, next0, ids1, index1, next1) {
             matches.push(m);
         }
@@ -113,12 +130,22 @@ pub fn greedy00(ids0: &[TokenId], ids1: &[TokenId]) -> Vec<Matched> {
         matches.push(m);
     }
 
-    matches
+    Ok(matches)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::interrupt::CancelToken;
+
+    fn next_match_point(
+        start_index0: usize,
+        start_index1: usize,
+        ids0: &[TokenId],
+        ids1: &[TokenId],
+    ) -> Option<(usize, usize)> {
+        super::next_match_point(start_index0, start_index1, ids0, ids1, Interrupt::none()).unwrap()
+    }
 
     #[test]
     fn test_find_next() {
@@ -283,4 +310,23 @@ mod tests {
         let matches = greedy00(&ids0, &ids1);
         assert_eq!(matches, vec![]);
     }
+
+    #[test]
+    fn test_greedy00_interruptible() {
+        let ids0 = vec![TokenId(0), TokenId(1), TokenId(2), TokenId(3)];
+        let ids1 = vec![TokenId(4), TokenId(1), TokenId(2), TokenId(5)];
+
+        let reports = std::cell::RefCell::new(vec![]);
+        let progress = |done, total| reports.borrow_mut().push((done, total));
+        let cancel = CancelToken::new();
+        let interrupt = Interrupt::new(Some(&cancel), Some(&progress));
+
+        let matches = greedy00_interruptible(&ids0, &ids1, interrupt);
+        assert_eq!(matches, Ok(greedy00(&ids0, &ids1)));
+        assert_eq!(*reports.borrow(), vec![(0, 4)]);
+
+        cancel.cancel();
+        let matches = greedy00_interruptible(&ids0, &ids1, interrupt);
+        assert_eq!(matches, Err(Cancelled));
+    }
 }
@@ -0,0 +1,142 @@
+//! Pairwise comparison of a set of inputs encoded with one shared vocabulary
+
+use crate::bpe::Bpe;
+use crate::chunked::{self, DEFAULT_CHUNK_SIZE, DEFAULT_SAMPLE_SIZE};
+use crate::interrupt::{Cancelled, Interrupt};
+use crate::matcher::greedy00_interruptible;
+use crate::similarity::{similarity, Similarity};
+use crate::token::TokenId;
+
+/// Pairwise similarity scores in `0.0..=1.0`, indexed by input. Symmetric,
+/// with 1.0 on the diagonal.
+pub type SimilarityMatrix = Vec<Vec<f64>>;
+
+/// Trains one vocabulary on a sample of every input, and encodes each input
+/// with it.
+pub fn encode_all(inputs: &[&[u8]]) -> (Bpe, Vec<Vec<TokenId>>) {
+    let bpe = Bpe::new(&chunked::training_sample(
+        inputs,
+        DEFAULT_CHUNK_SIZE,
+        DEFAULT_SAMPLE_SIZE,
+    ));
+    let encoded = inputs
+        .iter()
+        .map(|input| chunked::encode(&bpe, input, DEFAULT_CHUNK_SIZE))
+        .collect();
+    (bpe, encoded)
+}
+
+/// Length in bytes of every token of `bpe`, indexed by `TokenId`.
+pub fn token_lengths(bpe: &Bpe) -> Vec<usize> {
+    let mut lengths = Vec::with_capacity(bpe.ids_to_tokens().len());
+    for &id in bpe.ids_to_tokens().keys() {
+        debug_assert_eq!(id.index(), lengths.len(), "ids are assigned in order");
+        lengths.push(bpe.decode(vec![id]).len());
+    }
+    lengths
+}
+
+/// Similarity of every pair of `encoded` inputs: the `same_fraction` of their
+/// `greedy00` matches.
+pub fn similarity_matrix(encoded: &[Vec<TokenId>], lengths: &[usize]) -> SimilarityMatrix {
+    similarity_matrix_interruptible(encoded, lengths, Interrupt::none()).expect("not cancellable")
+}
+
+/// `similarity_matrix`, reporting progress through the pairs.
+pub fn similarity_matrix_interruptible(
+    encoded: &[Vec<TokenId>],
+    lengths: &[usize],
+    interrupt: Interrupt<'_>,
+) -> Result<SimilarityMatrix, Cancelled> {
+    let similarities = pairwise_interruptible(encoded, lengths, interrupt)?;
+    Ok(similarities
+        .iter()
+        .map(|row| row.iter().map(|scores| scores.same_fraction).collect())
+        .collect())
+}
+
+/// All `Similarity` scores of every pair of `encoded` inputs, from their
+/// `greedy00` matches.
+pub fn pairwise(encoded: &[Vec<TokenId>], lengths: &[usize]) -> Vec<Vec<Similarity>> {
+    pairwise_interruptible(encoded, lengths, Interrupt::none()).expect("not cancellable")
+}
+
+/// `pairwise`, reporting progress through the pairs.
+pub fn pairwise_interruptible(
+    encoded: &[Vec<TokenId>],
+    lengths: &[usize],
+    interrupt: Interrupt<'_>,
+) -> Result<Vec<Vec<Similarity>>, Cancelled> {
+    let n = encoded.len();
+    let pairs = n * n.saturating_sub(1) / 2;
+    let mut matrix = vec![vec![Similarity::IDENTICAL; n]; n];
+
+    let mut done = 0;
+    for i in 0..n {
+        for j in i + 1..n {
+            interrupt.check_now(done, pairs)?;
+            let matches =
+                greedy00_interruptible(&encoded[i], &encoded[j], interrupt.without_progress())?;
+            let scores = similarity(&matches, lengths);
+            matrix[i][j] = scores;
+            matrix[j][i] = scores;
+            done += 1;
+        }
+    }
+
+    Ok(matrix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interrupt::CancelToken;
+
+    #[test]
+    fn test_similarity_matrix() {
+        let inputs: [&[u8]; 3] = [b"abcdabcdabcd", b"abcdabcdabce", b"xyzxyzxyzxyz"];
+        let (bpe, encoded) = encode_all(&inputs);
+        for (input, encoded) in inputs.iter().zip(&encoded) {
+            assert_eq!(bpe.decode(encoded.clone()), *input);
+        }
+
+        let lengths = token_lengths(&bpe);
+        assert_eq!(lengths.len(), bpe.ids_to_tokens().len());
+        assert!(lengths[..256].iter().all(|&len| len == 1));
+
+        let matrix = similarity_matrix(&encoded, &lengths);
+        assert_eq!(matrix.len(), 3);
+        for i in 0..3 {
+            assert_eq!(matrix[i][i], 1.0);
+            for j in 0..3 {
+                assert_eq!(matrix[i][j], matrix[j][i]);
+            }
+        }
+        assert!(matrix[0][1] > 0.5, "{matrix:?}");
+        assert_eq!(matrix[0][2], 0.0);
+        assert_eq!(matrix[1][2], 0.0);
+
+        assert_eq!(similarity_matrix(&[], &lengths), SimilarityMatrix::new());
+
+        let similarities = pairwise(&encoded, &lengths);
+        assert_eq!(similarities[1][1], Similarity::IDENTICAL);
+        assert_eq!(similarities[0][1].same_fraction, matrix[0][1]);
+        assert_eq!(similarities[0][2].jaccard, 0.0);
+
+        let reports = std::cell::RefCell::new(vec![]);
+        let progress = |done, total| reports.borrow_mut().push((done, total));
+        let cancel = CancelToken::new();
+        let interrupt = Interrupt::new(Some(&cancel), Some(&progress));
+        assert_eq!(
+            similarity_matrix_interruptible(&encoded, &lengths, interrupt),
+            Ok(matrix)
+        );
+        assert_eq!(*reports.borrow(), [(0, 3), (1, 3), (2, 3)]);
+
+        cancel.cancel();
+        assert_eq!(
+            similarity_matrix_interruptible(&encoded, &lengths, interrupt),
+            Err(Cancelled)
+        );
+    }
+}
@@ -4,6 +4,9 @@ use crate::utils::{decrease_priorities, increase_priorities, MappedSets};
 use indexmap::{IndexMap, IndexSet};
 use keyed_priority_queue::KeyedPriorityQueue;
 
+/// Marks a pattern index whose token was merged into its predecessor.
+const EMPTY: TokenId = TokenId::RESERVED;
+
 pub struct RePair {
     ids_to_tokens: IndexMap<TokenId, Token>,
     tokens_to_ids: IndexMap<Token, TokenId>,
@@ -23,10 +26,9 @@ impl RePair {
             .collect()
     }
 
-    //note: using TokenId of usize::MAX to indicate empty index (refine/replace?)
     fn get_prev_id(ids: &[TokenId], index: usize) -> Option<(TokenId, usize)> {
         for (i, &id) in ids.iter().enumerate().take(index).rev() {
-            if id != TokenId(usize::MAX) {
+            if id != EMPTY {
                 return Some((id, i));
             };
         }
@@ -35,7 +37,7 @@ impl RePair {
 
     fn get_next_id(ids: &[TokenId], index: usize) -> Option<(TokenId, usize)> {
         for (i, &id) in ids.iter().enumerate().skip(index + 1) {
-            if id != TokenId(usize::MAX) {
+            if id != EMPTY {
                 return Some((id, i));
             };
         }
@@ -72,7 +74,7 @@ impl RePair {
             }
 
             *pattern.get_mut(index0).unwrap() = replacement;
-            *pattern.get_mut(index1).unwrap() = TokenId(usize::MAX);
+            *pattern.get_mut(index1).unwrap() = EMPTY;
         }
 
         (added_pair_locations, removed_pair_locations)
@@ -85,7 +87,7 @@ impl RePair {
             tokens_to_ids: IndexMap::new(),
         };
 
-        (0..=u8::MAX).for_each(|x| re_pair.add_id(TokenId(x as usize), Token::Byte(x)));
+        (0..=u8::MAX).for_each(|x| re_pair.add_id(TokenId::from(x), Token::Byte(x)));
 
         let mut patterns: Vec<Vec<TokenId>> = data.iter().map(|x| re_pair.encode(x)).collect();
 
@@ -106,7 +108,7 @@ impl RePair {
             if count < 2 {
                 break;
             }
-            let new_id = TokenId(re_pair.ids_to_tokens.len());
+            let new_id = TokenId::from_index(re_pair.ids_to_tokens.len());
             re_pair.add_id(new_id, Token::Merge(id0, id1));
 
             for (pattern, pair_locations) in patterns
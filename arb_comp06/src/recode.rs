@@ -1,3 +1,4 @@
+use crate::interrupt::{Cancelled, Interrupt};
 use crate::token::{self, Token, TokenId};
 use indexmap::{IndexMap, IndexSet};
 
@@ -5,17 +6,34 @@ pub fn condense(
     pattern: Vec<TokenId>,
     merge_if: impl Fn(TokenId, TokenId) -> Option<TokenId>,
 ) -> Vec<TokenId> {
+    condense_interruptible(pattern, merge_if, Interrupt::none()).expect("not cancellable")
+}
+
+/// `condense`, reporting after each merge pass how many of the tokens of
+/// `pattern` have been merged away, out of all of them.
+pub fn condense_interruptible(
+    pattern: Vec<TokenId>,
+    merge_if: impl Fn(TokenId, TokenId) -> Option<TokenId>,
+    interrupt: Interrupt<'_>,
+) -> Result<Vec<TokenId>, Cancelled> {
+    let total = pattern.len();
     let mut result = pattern;
 
     loop {
-        let merged = token::merge(result.iter().copied(), &merge_if);
+        let merged = token::merge_interruptible(
+            result.iter().copied(),
+            &merge_if,
+            result.len(),
+            interrupt.without_progress(),
+        )?;
         if result.len() == merged.len() {
             break;
         }
         result = merged;
+        interrupt.check_now(total - result.len(), total)?;
     }
 
-    result
+    Ok(result)
 }
 
 pub fn condense_and_collect(
@@ -114,15 +132,15 @@ mod tests {
         let bpe = Bpe::new(&[&[1, 2, 1, 2, 3, 4, 5, 6]]);
         let range = range(
             bpe.encode(&[1, 2, 3, 4, 5, 6]),
-            &bpe.ids_to_tokens(),
-            &bpe.tokens_to_ids(),
+            bpe.ids_to_tokens(),
+            bpe.tokens_to_ids(),
         );
-        println!("{:?}", range);
+        println!("{range:?}");
     }
 
     fn f(pattern1: Vec<TokenId>, pattern2: Vec<TokenId>, bpe: &Bpe) -> Vec<TokenId> {
-        let range = range(pattern2.clone(), &bpe.ids_to_tokens(), &bpe.tokens_to_ids());
-        println!("{:?}", range);
+        let range = range(pattern2.clone(), bpe.ids_to_tokens(), bpe.tokens_to_ids());
+        println!("{range:?}");
 
         let range_vec = range.iter().copied().collect::<Vec<_>>();
         print_tokens(range_vec, bpe, |_id| false);
@@ -136,15 +154,15 @@ mod tests {
 
         //            let merge_if = |id0: &TokenId, id1: &TokenId| -> bool { true };
 
-        let e = expand(pattern1.clone(), &bpe.ids_to_tokens());
-        println!("{:?}", e);
+        let e = expand(pattern1.clone(), bpe.ids_to_tokens());
+        println!("{e:?}");
 
         let c = condense(e.clone(), merge_if);
-        println!("{:?}", c);
+        println!("{c:?}");
 
-        print_tokens(pattern1.clone(), &bpe, |id| !range.contains(id));
-        print_tokens(e, &bpe, |id| !range.contains(id));
-        print_tokens(c.clone(), &bpe, |id| !range.contains(id));
+        print_tokens(pattern1.clone(), bpe, |id| !range.contains(id));
+        print_tokens(e, bpe, |id| !range.contains(id));
+        print_tokens(c.clone(), bpe, |id| !range.contains(id));
 
         c
     }
@@ -155,7 +173,7 @@ mod tests {
         rand::thread_rng().fill_bytes(&mut pattern1);
         let pattern1 = &pattern1;
 
-        let mut pattern2 = pattern1.clone();
+        let mut pattern2 = *pattern1;
         rand::thread_rng().fill_bytes(&mut pattern2[8..16]);
         let _pattern2 = &pattern2;
 
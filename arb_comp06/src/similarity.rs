@@ -0,0 +1,220 @@
+//! Similarity scores of two patterns, from their encodings with one vocabulary
+//!
+//! All scores are computed from the `Matched` blocks of the two encodings, so
+//! they are cheap once the patterns are matched.
+
+use crate::matcher::Matched;
+use crate::token::TokenId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Similarity scores of two patterns.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Similarity {
+    /// Fraction of the bytes of both patterns in `Matched::Same` blocks.
+    pub same_fraction: f64,
+    /// Jaccard index of the sets of tokens used by each pattern.
+    pub jaccard: f64,
+    /// Normalized compression distance: 0.0 for identical patterns, about 1.0
+    /// for unrelated ones.
+    pub ncd: f64,
+}
+
+impl Similarity {
+    /// Scores of a pattern compared with itself.
+    pub const IDENTICAL: Self = Self {
+        same_fraction: 1.0,
+        jaccard: 1.0,
+        ncd: 0.0,
+    };
+}
+
+/// A score to compare patterns by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    SameFraction,
+    Jaccard,
+    Ncd,
+}
+
+impl Metric {
+    /// Distance in `0.0..=1.0` of patterns with the scores `similarity`: 0.0
+    /// for identical patterns.
+    pub fn distance(self, similarity: &Similarity) -> f64 {
+        match self {
+            Metric::SameFraction => 1.0 - similarity.same_fraction,
+            Metric::Jaccard => 1.0 - similarity.jaccard,
+            Metric::Ncd => similarity.ncd,
+        }
+    }
+}
+
+/// Fraction of the bytes of both matched patterns that are in `Matched::Same`
+/// blocks, or 1.0 if both are empty. `lengths` are the token lengths.
+pub fn same_fraction(matches: &[Matched], lengths: &[usize]) -> f64 {
+    let bytes = |ids: &[TokenId]| ids.iter().map(|id| lengths[id.index()]).sum::<usize>();
+
+    let mut same = 0;
+    let mut total = 0;
+    for matched in matches {
+        match matched {
+            Matched::Same(ids) => {
+                same += 2 * bytes(ids);
+                total += 2 * bytes(ids);
+            }
+            Matched::Diff(ids0, ids1) => total += bytes(ids0) + bytes(ids1),
+        }
+    }
+
+    if total == 0 {
+        1.0
+    } else {
+        same as f64 / total as f64
+    }
+}
+
+/// Size of the intersection over the size of the union of the distinct
+/// tokens of `ids0` and `ids1`, or 1.0 if both are empty.
+pub fn jaccard(ids0: &[TokenId], ids1: &[TokenId]) -> f64 {
+    let set0: HashSet<_> = ids0.iter().collect();
+    let set1: HashSet<_> = ids1.iter().collect();
+    let union = set0.union(&set1).count();
+    if union == 0 {
+        1.0
+    } else {
+        set0.intersection(&set1).count() as f64 / union as f64
+    }
+}
+
+/// Normalized compression distance of the matched patterns, with the trained
+/// grammar as the compressor.
+///
+/// A pattern compresses to its tokens. Compressed together, each
+/// `Matched::Same` block is stored once and referenced by one token from the
+/// other pattern. The distance is `(C(xy) - min(C(x), C(y))) / max(C(x), C(y))`,
+/// or 0.0 if both patterns are empty. Like any NCD, it overstates the
+/// distance of patterns that encode to only a few tokens.
+pub fn ncd(matches: &[Matched]) -> f64 {
+    let mut size0 = 0;
+    let mut size1 = 0;
+    let mut together = 0;
+    for matched in matches {
+        match matched {
+            Matched::Same(ids) => {
+                size0 += ids.len();
+                size1 += ids.len();
+                together += ids.len() + 1;
+            }
+            Matched::Diff(ids0, ids1) => {
+                size0 += ids0.len();
+                size1 += ids1.len();
+                together += ids0.len() + ids1.len();
+            }
+        }
+    }
+
+    let max = size0.max(size1);
+    if max == 0 {
+        0.0
+    } else {
+        let distance = together.saturating_sub(size0.min(size1)) as f64 / max as f64;
+        distance.min(1.0)
+    }
+}
+
+/// All scores of the matched patterns. `lengths` are the token lengths.
+pub fn similarity(matches: &[Matched], lengths: &[usize]) -> Similarity {
+    let mut ids0 = vec![];
+    let mut ids1 = vec![];
+    for matched in matches {
+        match matched {
+            Matched::Same(ids) => {
+                ids0.extend_from_slice(ids);
+                ids1.extend_from_slice(ids);
+            }
+            Matched::Diff(diff0, diff1) => {
+                ids0.extend_from_slice(diff0);
+                ids1.extend_from_slice(diff1);
+            }
+        }
+    }
+
+    Similarity {
+        same_fraction: same_fraction(matches, lengths),
+        jaccard: jaccard(&ids0, &ids1),
+        ncd: ncd(matches),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[u32]) -> Vec<TokenId> {
+        ids.iter().copied().map(TokenId).collect()
+    }
+
+    #[test]
+    fn test_same_fraction() {
+        let lengths = [1, 1, 3];
+        let matches = [
+            Matched::Same(ids(&[2])),
+            Matched::Diff(ids(&[0]), ids(&[1, 1])),
+        ];
+        assert_eq!(same_fraction(&matches, &lengths), 6.0 / 9.0);
+        assert_eq!(same_fraction(&[], &lengths), 1.0);
+        assert_eq!(
+            same_fraction(&[Matched::Diff(ids(&[0]), vec![])], &lengths),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_jaccard() {
+        assert_eq!(jaccard(&ids(&[0, 1, 1, 2]), &ids(&[2, 1, 3])), 2.0 / 4.0);
+        assert_eq!(jaccard(&ids(&[0, 0]), &ids(&[0])), 1.0);
+        assert_eq!(jaccard(&ids(&[0]), &ids(&[1])), 0.0);
+        assert_eq!(jaccard(&[], &[]), 1.0);
+    }
+
+    #[test]
+    fn test_ncd() {
+        // Identical patterns cost one reference more than either alone.
+        assert_eq!(ncd(&[Matched::Same(ids(&[0, 1, 2, 3]))]), 1.0 / 4.0);
+        assert_eq!(ncd(&[Matched::Diff(ids(&[0, 1]), ids(&[2]))]), 1.0);
+        assert_eq!(ncd(&[]), 0.0);
+
+        let matches = [
+            Matched::Same(ids(&[0, 1, 2, 3, 4, 5])),
+            Matched::Diff(ids(&[6]), ids(&[7, 8])),
+        ];
+        // Alone 7 and 8 tokens, together 6 + 1 for the reference + 1 + 2.
+        assert_eq!(ncd(&matches), 3.0 / 8.0);
+    }
+
+    #[test]
+    fn test_similarity() {
+        let lengths = [1; 4];
+        let matches = [
+            Matched::Same(ids(&[0, 1])),
+            Matched::Diff(ids(&[2]), ids(&[3])),
+        ];
+        assert_eq!(
+            similarity(&matches, &lengths),
+            Similarity {
+                same_fraction: 4.0 / 6.0,
+                jaccard: 2.0 / 4.0,
+                ncd: 2.0 / 3.0,
+            }
+        );
+
+        let identical = Similarity::IDENTICAL;
+        for metric in [Metric::SameFraction, Metric::Jaccard, Metric::Ncd] {
+            assert_eq!(metric.distance(&identical), 0.0, "{metric:?}");
+        }
+        let scores = similarity(&matches, &lengths);
+        assert_eq!(Metric::SameFraction.distance(&scores), 1.0 - 4.0 / 6.0);
+        assert_eq!(Metric::Jaccard.distance(&scores), 0.5);
+        assert_eq!(Metric::Ncd.distance(&scores), 2.0 / 3.0);
+    }
+}
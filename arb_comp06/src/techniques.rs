@@ -1,13 +1,68 @@
 //! Techniques for comparing patterns
 
+use crate::bpe::Bpe;
+use crate::interrupt::{Cancelled, Interrupt};
+use crate::recode::{condense_interruptible, expand, range};
+use crate::token::{Token, TokenId};
+
+/// A run of tokens of one pattern that are all in, or all out of, the range
+/// of another pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub ids: Vec<TokenId>,
+    /// Whether the tokens are in the other pattern's range: the ids in it and
+    /// reachable from it by merges of `recode::range`.
+    pub in_other: bool,
+}
+
+/// Technique 1: `pattern1` in terms of `pattern2`.
+///
+/// Expands `pattern1` to bytes, then condenses it again with only the merges
+/// whose two children are both in or both out of the range of `pattern2`.
+/// Both patterns must be encoded with `bpe`.
+pub fn in_terms_of(bpe: &Bpe, pattern1: &[TokenId], pattern2: &[TokenId]) -> Vec<Segment> {
+    in_terms_of_interruptible(bpe, pattern1, pattern2, Interrupt::none()).expect("not cancellable")
+}
+
+/// `in_terms_of`, reporting progress through each merge pass.
+pub fn in_terms_of_interruptible(
+    bpe: &Bpe,
+    pattern1: &[TokenId],
+    pattern2: &[TokenId],
+    interrupt: Interrupt<'_>,
+) -> Result<Vec<Segment>, Cancelled> {
+    let in_pattern2 = range(pattern2.to_vec(), bpe.ids_to_tokens(), bpe.tokens_to_ids());
+    interrupt.cancelled()?;
+
+    let merge_if = |id0: TokenId, id1: TokenId| -> Option<TokenId> {
+        bpe.tokens_to_ids()
+            .get(&Token::Merge(id0, id1))
+            .filter(|_| in_pattern2.contains(&id0) == in_pattern2.contains(&id1))
+            .copied()
+    };
+    let expanded = expand(pattern1.to_vec(), bpe.ids_to_tokens());
+    let condensed = condense_interruptible(expanded, merge_if, interrupt)?;
+
+    let mut segments: Vec<Segment> = vec![];
+    for id in condensed {
+        let in_other = in_pattern2.contains(&id);
+        match segments.last_mut() {
+            Some(segment) if segment.in_other == in_other => segment.ids.push(id),
+            _ => segments.push(Segment {
+                ids: vec![id],
+                in_other,
+            }),
+        }
+    }
+    Ok(segments)
+}
+
 #[cfg(test)]
 mod tests {
 
-    use crate::bpe::Bpe;
+    use super::*;
     use crate::matcher;
-    use crate::recode::{condense, expand, range};
     use crate::test_utils::{self, print_tokens};
-    use crate::token::{Token, TokenId};
 
     #[test]
     fn technique01() {
@@ -27,19 +82,36 @@ mod tests {
         let pattern1 = bpe.encode(file1.as_bytes());
         let pattern2 = bpe.encode(file2.as_bytes());
 
-        let in_pattern2 = range(pattern2.clone(), bpe.ids_to_tokens(), bpe.tokens_to_ids());
-
-        let merge_if = |id0: TokenId, id1: TokenId| -> Option<TokenId> {
-            bpe.tokens_to_ids()
-                .get(&Token::Merge(id0, id1))
-                .filter(|_| in_pattern2.contains(&id0) == in_pattern2.contains(&id1))
-                .copied()
-        };
-
-        let e = expand(pattern1.clone(), bpe.ids_to_tokens());
-        let c = condense(e.clone(), merge_if);
-
-        print_tokens(c, &bpe, |id| !in_pattern2.contains(id));
+        let segments = in_terms_of(&bpe, &pattern1, &pattern2);
+        for segment in &segments {
+            print_tokens(segment.ids.clone(), &bpe, |_| !segment.in_other);
+        }
+
+        let decoded: Vec<(String, bool)> = segments
+            .iter()
+            .map(|segment| {
+                let bytes = bpe.decode(segment.ids.clone());
+                (String::from_utf8(bytes).unwrap(), segment.in_other)
+            })
+            .collect();
+        assert_eq!(
+            decoded.iter().map(|(s, _)| s.as_str()).collect::<String>(),
+            file1
+        );
+        assert!(decoded
+            .iter()
+            .filter(|(_, in_other)| !in_other)
+            .all(|(s, _)| s.chars().all(|c| "12345 ".contains(c))));
+        assert!(
+            decoded
+                .iter()
+                .any(|(s, in_other)| !in_other && s.contains('1')),
+            "{decoded:?}"
+        );
+        assert!(
+            segments.windows(2).all(|w| w[0].in_other != w[1].in_other),
+            "adjacent segments alternate"
+        );
     }
 
     #[test]
@@ -1,6 +1,9 @@
 use crate::bpe::Bpe;
+use crate::interrupt::{Cancelled, Interrupt};
 use crate::matcher::Matched;
-use crate::token::TokenId;
+use crate::techniques::Segment;
+use crate::three_way::Region;
+use crate::token::{Token, TokenId};
 use colored::*;
 
 fn color(c: usize, s: &str) -> ColoredString {
@@ -36,22 +39,85 @@ pub fn print_tokens(
         let s = format!("{:?}", bpe.decode(vec![id]));
 
         let s = if highlight(&id) {
-            color_highlight(id.0, &s)
+            color_highlight(id.index(), &s)
         } else {
-            color(id.0, &s)
+            color(id.index(), &s)
         };
         print!("{s}");
     }
     println!();
 }
 
+fn merge_tree_rows(id: TokenId, bpe: &Bpe) -> Vec<(String, TokenId)> {
+    fn add_rows(
+        id: TokenId,
+        bpe: &Bpe,
+        prefix: &str,
+        child_prefix: &str,
+        rows: &mut Vec<(String, TokenId)>,
+    ) {
+        rows.push((prefix.to_string(), id));
+
+        if let Some(&Token::Merge(id0, id1)) = bpe.ids_to_tokens().get(&id) {
+            add_rows(
+                id0,
+                bpe,
+                &format!("{child_prefix}├── "),
+                &format!("{child_prefix}│   "),
+                rows,
+            );
+            add_rows(
+                id1,
+                bpe,
+                &format!("{child_prefix}└── "),
+                &format!("{child_prefix}    "),
+                rows,
+            );
+        }
+    }
+
+    let mut rows = vec![];
+    add_rows(id, bpe, "", "", &mut rows);
+    rows
+}
+
+fn merge_tree_label(id: TokenId, bpe: &Bpe) -> String {
+    let bytes = bpe.decode(vec![id]);
+    let hex = bytes
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("{} [{hex}] {:?}", id.0, String::from_utf8_lossy(&bytes))
+}
+
+/// Renders the merge tree of token `id` as indented text lines.
+pub fn merge_tree_text(id: TokenId, bpe: &Bpe) -> String {
+    merge_tree_rows(id, bpe)
+        .into_iter()
+        .map(|(prefix, id)| format!("{prefix}{}\n", merge_tree_label(id, bpe)))
+        .collect()
+}
+
+pub fn print_merge_tree(id: TokenId, bpe: &Bpe) {
+    for (prefix, id) in merge_tree_rows(id, bpe) {
+        println!("{prefix}{}", color(id.index(), &merge_tree_label(id, bpe)));
+    }
+}
+
 fn print_columns(
     left: impl IntoIterator<Item = ColoredString>,
     right: impl IntoIterator<Item = ColoredString>,
 ) {
+    print_n_columns(vec![
+        Box::new(left.into_iter()),
+        Box::new(right.into_iter()),
+    ]);
+}
+
+fn print_n_columns<'a>(columns: Vec<Box<dyn Iterator<Item = ColoredString> + 'a>>) {
     let width = 8;
-    let mut left = left.into_iter().peekable();
-    let mut right = right.into_iter().peekable();
+    let mut columns: Vec<_> = columns.into_iter().map(Iterator::peekable).collect();
 
     fn row_section(width: usize, i: &mut impl Iterator<Item = ColoredString>) {
         for c in 0..width {
@@ -66,10 +132,13 @@ fn print_columns(
         }
     }
 
-    while left.peek().is_some() || right.peek().is_some() {
-        row_section(width, &mut left);
-        print!(" - ");
-        row_section(width, &mut right);
+    while columns.iter_mut().any(|column| column.peek().is_some()) {
+        for (i, column) in columns.iter_mut().enumerate() {
+            if i != 0 {
+                print!(" - ");
+            }
+            row_section(width, column);
+        }
         println!();
     }
 }
@@ -85,9 +154,9 @@ fn colored_hex(
         .map(|s| {
             assert_eq!(2, s.chars().count());
             if highlight {
-                color_highlight(id.0, &s)
+                color_highlight(id.index(), &s)
             } else {
-                color(id.0, &s)
+                color(id.index(), &s)
             }
         })
         .collect()
@@ -111,9 +180,9 @@ fn colored_ascii(
         .map(|s| {
             assert_eq!(2, s.chars().count());
             if highlight {
-                color_highlight(id.0, &s)
+                color_highlight(id.index(), &s)
             } else {
-                color(id.0, &s)
+                color(id.index(), &s)
             }
         })
         .collect()
@@ -128,9 +197,9 @@ fn print_colored_id_lists(highlight: bool, left: &[TokenId], right: &[TokenId])
         let s = format!("{}", id.0);
 
         let s = if highlight {
-            color_highlight(id.0, &s)
+            color_highlight(id.index(), &s)
         } else {
-            color(id.0, &s)
+            color(id.index(), &s)
         };
         print!("[{s}] ");
     };
@@ -186,18 +255,23 @@ pub fn print_ui_01(
 
 //todo: dedup w/ colored_hex?
 fn hex_cells(diff: bool, id: TokenId, decode: impl Fn(&Vec<TokenId>) -> Vec<u8>) -> Vec<HexCell> {
-    decode(&vec![id])
+    let bytes = decode(&vec![id]);
+    bytes
         .iter()
-        .map(|&b| {
+        .enumerate()
+        .map(|(offset, &b)| {
+            let provenance = Provenance::new(offset as u32);
             if diff {
                 HexCell::Diff {
                     value: b,
-                    source_id: id.0,
+                    source_id: id.index(),
+                    provenance,
                 }
             } else {
                 HexCell::Same {
                     value: b,
-                    source_id: id.0,
+                    source_id: id.index(),
+                    provenance,
                 }
             }
         })
@@ -209,64 +283,308 @@ pub fn matches_to_cells(
     matches: &[Matched],
     decode: impl Fn(&Vec<TokenId>) -> Vec<u8>,
 ) -> (Vec<HexCell>, Vec<HexCell>) {
+    matches_to_cells_interruptible(matches, decode, Interrupt::none()).expect("not cancellable")
+}
+
+/// `matches_to_cells`, reporting progress through `matches`.
+pub fn matches_to_cells_interruptible(
+    matches: &[Matched],
+    decode: impl Fn(&Vec<TokenId>) -> Vec<u8>,
+    interrupt: Interrupt<'_>,
+) -> Result<(Vec<HexCell>, Vec<HexCell>), Cancelled> {
     let mut cells0 = vec![];
     let mut cells1 = vec![];
 
-    matches.iter().for_each(|matched| match matched {
-        Matched::Same(ids) => {
-            for &id in ids {
-                cells0.append(&mut hex_cells(false, id, &decode));
-                cells1.append(&mut hex_cells(false, id, &decode));
-            }
-        }
-        Matched::Diff(ids0, ids1) => {
-            let mut block_cells0 = vec![];
-            let mut block_cells1 = vec![];
+    for (i, matched) in matches.iter().enumerate() {
+        interrupt.check(i, i, matches.len())?;
 
-            for &id in ids0 {
-                block_cells0.append(&mut hex_cells(true, id, &decode));
-            }
-            for &id in ids1 {
-                block_cells1.append(&mut hex_cells(true, id, &decode));
+        match matched {
+            Matched::Same(ids) => {
+                for &id in ids {
+                    cells0.append(&mut hex_cells(false, id, &decode));
+                    cells1.append(&mut hex_cells(false, id, &decode));
+                }
             }
+            Matched::Diff(ids0, ids1) => {
+                let mut block_cells0 = vec![];
+                let mut block_cells1 = vec![];
+
+                for &id in ids0 {
+                    block_cells0.append(&mut hex_cells(true, id, &decode));
+                }
+                for &id in ids1 {
+                    block_cells1.append(&mut hex_cells(true, id, &decode));
+                }
 
-            while block_cells0.len() < block_cells1.len() {
-                block_cells0.push(HexCell::Blank);
+                while block_cells0.len() < block_cells1.len() {
+                    block_cells0.push(HexCell::Blank);
+                }
+
+                while block_cells1.len() < block_cells0.len() {
+                    block_cells1.push(HexCell::Blank);
+                }
+
+                cells0.append(&mut block_cells0);
+                cells1.append(&mut block_cells1);
             }
+        }
+    }
 
-            while block_cells1.len() < block_cells0.len() {
-                block_cells1.push(HexCell::Blank);
+    Ok((cells0, cells1))
+}
+
+/// Cells of the tokens of `segments`, shown as different where they are out
+/// of the other pattern's range.
+pub fn segments_to_cells(
+    segments: &[Segment],
+    decode: impl Fn(&Vec<TokenId>) -> Vec<u8>,
+) -> Vec<HexCell> {
+    segments_to_cells_interruptible(segments, decode, Interrupt::none()).expect("not cancellable")
+}
+
+/// `segments_to_cells`, reporting progress through the tokens of `segments`.
+pub fn segments_to_cells_interruptible(
+    segments: &[Segment],
+    decode: impl Fn(&Vec<TokenId>) -> Vec<u8>,
+    interrupt: Interrupt<'_>,
+) -> Result<Vec<HexCell>, Cancelled> {
+    let total = segments.iter().map(|segment| segment.ids.len()).sum();
+    let mut done = 0;
+    let mut cells = vec![];
+    for segment in segments {
+        for &id in &segment.ids {
+            interrupt.check(done, done, total)?;
+            done += 1;
+            cells.append(&mut hex_cells(!segment.in_other, id, &decode));
+        }
+    }
+    Ok(cells)
+}
+
+/// How the tokens of a three-way region are shown in one column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Kept,
+    Changed,
+    Conflict,
+}
+
+/// The tokens of `region` in the base, A and B columns, and how each is shown.
+fn region_columns(region: &Region) -> [(&[TokenId], Change); 3] {
+    use Change::*;
+    match region {
+        Region::Unchanged(ids) => [(ids, Kept), (ids, Kept), (ids, Kept)],
+        Region::ChangedA(base, a) => [(base, Changed), (a, Changed), (base, Kept)],
+        Region::ChangedB(base, b) => [(base, Changed), (base, Kept), (b, Changed)],
+        Region::ChangedBoth(base, changed) => {
+            [(base, Changed), (changed, Changed), (changed, Changed)]
+        }
+        Region::Conflict(base, a, b) => [(base, Conflict), (a, Conflict), (b, Conflict)],
+    }
+}
+
+fn region_label(region: &Region) -> Option<ColoredString> {
+    match region {
+        Region::Unchanged(_) => None,
+        Region::ChangedA(..) => Some("changed in A".bold()),
+        Region::ChangedB(..) => Some("changed in B".bold()),
+        Region::ChangedBoth(..) => Some("changed in A and B".bold()),
+        Region::Conflict(..) => Some("conflict".red().bold()),
+    }
+}
+
+/// Prints three-way `regions` as base, A and B columns.
+pub fn print_ui_03(regions: &[Region], decode: impl Fn(&Vec<TokenId>) -> Vec<u8>) {
+    let decode = &decode;
+    for region in regions {
+        if let Some(label) = region_label(region) {
+            println!("{label}");
+        }
+        let columns = region_columns(region);
+        print_n_columns(
+            columns
+                .iter()
+                .map(|&(ids, change)| {
+                    let highlight = change != Change::Kept;
+                    Box::new(
+                        ids.iter()
+                            .flat_map(move |&id| colored_hex(highlight, id, decode)),
+                    ) as Box<dyn Iterator<Item = ColoredString>>
+                })
+                .collect(),
+        );
+        print_n_columns(
+            columns
+                .iter()
+                .map(|&(ids, change)| {
+                    let highlight = change != Change::Kept;
+                    Box::new(
+                        ids.iter()
+                            .flat_map(move |&id| colored_ascii(highlight, id, decode)),
+                    ) as Box<dyn Iterator<Item = ColoredString>>
+                })
+                .collect(),
+        );
+    }
+}
+
+pub type ThreeWayCells = (Vec<HexCell>, Vec<HexCell>, Vec<HexCell>);
+
+pub fn regions_to_cells(
+    regions: &[Region],
+    decode: impl Fn(&Vec<TokenId>) -> Vec<u8>,
+) -> ThreeWayCells {
+    regions_to_cells_interruptible(regions, decode, Interrupt::none()).expect("not cancellable")
+}
+
+/// Cells of the base, A and B columns of three-way `regions`, reporting
+/// progress through `regions`. Each region is padded to the same length in
+/// every column.
+pub fn regions_to_cells_interruptible(
+    regions: &[Region],
+    decode: impl Fn(&Vec<TokenId>) -> Vec<u8>,
+    interrupt: Interrupt<'_>,
+) -> Result<ThreeWayCells, Cancelled> {
+    let mut cells: [Vec<HexCell>; 3] = Default::default();
+
+    for (i, region) in regions.iter().enumerate() {
+        interrupt.check(i, i, regions.len())?;
+
+        let mut blocks = region_columns(region).map(|(ids, change)| {
+            let mut block = vec![];
+            for &id in ids {
+                let bytes = decode(&vec![id]);
+                block.extend(bytes.into_iter().enumerate().map(|(offset, value)| {
+                    let source_id = id.index();
+                    let provenance = Provenance::new(offset as u32);
+                    match change {
+                        Change::Kept => HexCell::Same {
+                            value,
+                            source_id,
+                            provenance,
+                        },
+                        Change::Changed => HexCell::Diff {
+                            value,
+                            source_id,
+                            provenance,
+                        },
+                        Change::Conflict => HexCell::Conflict {
+                            value,
+                            source_id,
+                            provenance,
+                        },
+                    }
+                }));
             }
+            block
+        });
 
-            cells0.append(&mut block_cells0);
-            cells1.append(&mut block_cells1);
+        let len = blocks.iter().map(Vec::len).max().unwrap_or(0);
+        for (cells, block) in cells.iter_mut().zip(&mut blocks) {
+            block.resize(len, HexCell::Blank);
+            cells.append(block);
         }
-    });
+    }
 
-    (cells0, cells1)
+    let [base, a, b] = cells;
+    Ok((base, a, b))
 }
 
 // test interface for very lightweight frontend
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HexCell {
-    Same { value: u8, source_id: usize },
-    Diff { value: u8, source_id: usize },
+    Same {
+        value: u8,
+        source_id: usize,
+        provenance: Provenance,
+    },
+    Diff {
+        value: u8,
+        source_id: usize,
+        provenance: Provenance,
+    },
+    /// Part of a region changed differently in A and B of a three-way diff.
+    Conflict {
+        value: u8,
+        source_id: usize,
+        provenance: Provenance,
+    },
     Blank,
 }
 
+impl HexCell {
+    /// The id of the token that produced the byte, and where the byte is in
+    /// it. None for a blank cell.
+    pub fn source(&self) -> Option<(usize, Provenance)> {
+        match *self {
+            HexCell::Same {
+                source_id,
+                provenance,
+                ..
+            }
+            | HexCell::Diff {
+                source_id,
+                provenance,
+                ..
+            }
+            | HexCell::Conflict {
+                source_id,
+                provenance,
+                ..
+            } => Some((source_id, provenance)),
+            HexCell::Blank => None,
+        }
+    }
+}
+
+/// Where the byte of a `HexCell` is in the token that produced it.
+///
+/// Facts about the whole token, like its length, are in the vocabulary, by
+/// the cell's `source_id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Provenance {
+    /// Offset of the byte in the token.
+    pub offset: u32,
+}
+
+impl Provenance {
+    /// A byte compared on its own, not as part of a token.
+    pub const BYTE: Self = Self::new(0);
+
+    pub const fn new(offset: u32) -> Self {
+        Self { offset }
+    }
+
+    /// Whether the byte is the first of its token.
+    pub fn is_token_start(self) -> bool {
+        self.offset == 0
+    }
+}
+
 pub fn print_ui_02(cells0: &[HexCell], cells1: &[HexCell]) {
     fn f(cell: &HexCell) -> ColoredString {
         match cell {
-            HexCell::Same { value, source_id } => {
+            HexCell::Same {
+                value, source_id, ..
+            } => {
                 let s = format!("{value:02x}");
                 assert_eq!(2, s.chars().count());
                 color(*source_id, &s)
             }
-            HexCell::Diff { value, source_id } => {
+            HexCell::Diff {
+                value, source_id, ..
+            } => {
                 let s = format!("{value:02x}");
                 assert_eq!(2, s.chars().count());
                 color_highlight(*source_id, &s)
             }
+            HexCell::Conflict {
+                value, source_id, ..
+            } => {
+                let s = format!("{value:02x}");
+                assert_eq!(2, s.chars().count());
+                color_highlight(*source_id, &s).underline()
+            }
             HexCell::Blank => "__".white(),
         }
     }
@@ -277,6 +595,7 @@ pub fn print_ui_02(cells0: &[HexCell], cells1: &[HexCell]) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::interrupt::CancelToken;
 
     #[test]
     fn test_print_ui_01() {
@@ -301,6 +620,25 @@ mod tests {
         println!();
     }
 
+    #[test]
+    fn test_merge_tree_text() {
+        let bpe = Bpe::new(&["abcbcd".as_bytes(), "abcabc".as_bytes()]);
+
+        print_merge_tree(TokenId(257), &bpe);
+
+        let expected = [
+            "257 [61 62 63] \"abc\"",
+            "├── 97 [61] \"a\"",
+            "└── 256 [62 63] \"bc\"",
+            "    ├── 98 [62] \"b\"",
+            "    └── 99 [63] \"c\"",
+        ];
+        assert_eq!(
+            merge_tree_text(TokenId(257), &bpe),
+            expected.map(|line| format!("{line}\n")).concat()
+        );
+    }
+
     #[test]
     fn test_print_ui_02() {
         let pattern0 = "test abc".as_bytes();
@@ -320,4 +658,124 @@ mod tests {
 
         print_ui_02(&cells0, &cells1);
     }
+
+    #[test]
+    fn test_segments_to_cells() {
+        let bpe = Bpe::new(&["abab".as_bytes()]);
+        let decode = |x: &Vec<TokenId>| bpe.decode(x.clone());
+        let segments = [
+            Segment {
+                ids: vec![TokenId(256)],
+                in_other: true,
+            },
+            Segment {
+                ids: vec![TokenId(99)],
+                in_other: false,
+            },
+        ];
+        assert_eq!(
+            segments_to_cells(&segments, decode),
+            [
+                HexCell::Same {
+                    value: b'a',
+                    source_id: 256,
+                    provenance: Provenance::new(0)
+                },
+                HexCell::Same {
+                    value: b'b',
+                    source_id: 256,
+                    provenance: Provenance::new(1)
+                },
+                HexCell::Diff {
+                    value: b'c',
+                    source_id: 99,
+                    provenance: Provenance::BYTE
+                },
+            ]
+        );
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let interrupt = Interrupt::new(Some(&cancel), None);
+        assert_eq!(
+            segments_to_cells_interruptible(&segments, decode, interrupt),
+            Err(Cancelled)
+        );
+
+        let cells = segments_to_cells(&segments, decode);
+        assert_eq!(cells[1].source(), Some((256, Provenance::new(1))));
+        assert_eq!(cells[2].source(), Some((99, Provenance::BYTE)));
+        assert_eq!(HexCell::Blank.source(), None);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_hex_cell_size() {
+        // Every byte of the diffed files has a cell.
+        assert_eq!(std::mem::size_of::<HexCell>(), 16);
+    }
+
+    #[test]
+    fn test_print_ui_03() {
+        let base = "test abc xyz".as_bytes();
+        let a = "test 123 abc xyz".as_bytes();
+        let b = "test abc XYZ".as_bytes();
+
+        let bpe = Bpe::new(&[base, a, b]);
+        let regions =
+            crate::three_way::greedy00_three_way(&bpe.encode(base), &bpe.encode(a), &bpe.encode(b));
+        let decode = |x: &Vec<TokenId>| bpe.decode(x.clone());
+
+        print_ui_03(&regions, decode);
+
+        let (cells_base, cells_a, cells_b) = regions_to_cells(&regions, decode);
+        assert_eq!(cells_base.len(), cells_a.len());
+        assert_eq!(cells_base.len(), cells_b.len());
+
+        let values = |cells: &[HexCell]| -> Vec<u8> {
+            cells
+                .iter()
+                .filter_map(|cell| match *cell {
+                    HexCell::Same { value, .. }
+                    | HexCell::Diff { value, .. }
+                    | HexCell::Conflict { value, .. } => Some(value),
+                    HexCell::Blank => None,
+                })
+                .collect()
+        };
+        assert_eq!(values(&cells_base), base);
+        assert_eq!(values(&cells_a), a);
+        assert_eq!(values(&cells_b), b);
+
+        // A conflict is shown as conflicting in every column.
+        let conflict = Region::Conflict(
+            vec![TokenId(97)],
+            vec![TokenId(98)],
+            vec![TokenId(99), TokenId(100)],
+        );
+        let (cells_base, cells_a, cells_b) = regions_to_cells(&[conflict], decode);
+        assert_eq!(
+            cells_base,
+            [
+                HexCell::Conflict {
+                    value: b'a',
+                    source_id: 97,
+                    provenance: Provenance::BYTE
+                },
+                HexCell::Blank
+            ]
+        );
+        assert_eq!(
+            cells_a,
+            [
+                HexCell::Conflict {
+                    value: b'b',
+                    source_id: 98,
+                    provenance: Provenance::BYTE
+                },
+                HexCell::Blank
+            ]
+        );
+        assert_eq!(cells_b.len(), 2);
+    }
 }
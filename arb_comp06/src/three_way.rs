@@ -0,0 +1,284 @@
+//! Three-way comparison of two patterns (A and B) against a common base
+//!
+//! Built on the two-way `Matched` output: each `Matched::Diff` of the base
+//! against A or B is a hunk of base tokens replaced in that pattern. Hunks of
+//! A and B that overlap or touch are combined into one region, which is
+//! classified by which patterns changed it.
+
+use crate::interrupt::{Cancelled, Interrupt};
+use crate::matcher::{greedy00_interruptible, Matched};
+use crate::token::TokenId;
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Region {
+    /// Tokens the same in the base, A and B.
+    Unchanged(Vec<TokenId>),
+    /// Base tokens and their replacement in A. B is the same as the base.
+    ChangedA(Vec<TokenId>, Vec<TokenId>),
+    /// Base tokens and their replacement in B. A is the same as the base.
+    ChangedB(Vec<TokenId>, Vec<TokenId>),
+    /// Base tokens and the replacement both A and B made.
+    ChangedBoth(Vec<TokenId>, Vec<TokenId>),
+    /// Base tokens with different replacements in A and B.
+    Conflict(Vec<TokenId>, Vec<TokenId>, Vec<TokenId>),
+}
+
+/// Base tokens `range` replaced by `replacement`.
+struct Hunk<'a> {
+    range: Range<usize>,
+    replacement: &'a [TokenId],
+}
+
+/// The base tokens of `matches`, and its hunks in base order.
+fn hunks(matches: &[Matched]) -> (Vec<TokenId>, Vec<Hunk<'_>>) {
+    let mut base = vec![];
+    let mut hunks = vec![];
+    for matched in matches {
+        match matched {
+            Matched::Same(ids) => base.extend_from_slice(ids),
+            Matched::Diff(ids0, ids1) => {
+                let start = base.len();
+                base.extend_from_slice(ids0);
+                hunks.push(Hunk {
+                    range: start..base.len(),
+                    replacement: ids1,
+                });
+            }
+        }
+    }
+    (base, hunks)
+}
+
+/// `base[range]` with `hunks`, which must lie within `range`, applied.
+fn apply(base: &[TokenId], range: Range<usize>, hunks: &[Hunk]) -> Vec<TokenId> {
+    let mut result = vec![];
+    let mut index = range.start;
+    for hunk in hunks {
+        result.extend_from_slice(&base[index..hunk.range.start]);
+        result.extend_from_slice(hunk.replacement);
+        index = hunk.range.end;
+    }
+    result.extend_from_slice(&base[index..range.end]);
+    result
+}
+
+/// Classifies the regions of the base changed by A, B or both.
+///
+/// `base_a` and `base_b` are matches of the base (as pattern 0) against A
+/// and B. Changes of A and B that touch without overlapping are still
+/// reported as a conflict, since their order in a merge is ambiguous.
+///
+/// Panics if `base_a` and `base_b` don't have the same base tokens.
+pub fn three_way(base_a: &[Matched], base_b: &[Matched]) -> Vec<Region> {
+    let (base, hunks_a) = hunks(base_a);
+    let (base_of_b, hunks_b) = hunks(base_b);
+    assert_eq!(base, base_of_b, "matches must be against the same base");
+
+    let mut regions = vec![];
+    let mut index = 0;
+    let (mut next_a, mut next_b) = (0, 0);
+
+    loop {
+        let start = match (hunks_a.get(next_a), hunks_b.get(next_b)) {
+            (Some(a), Some(b)) => a.range.start.min(b.range.start),
+            (Some(a), None) => a.range.start,
+            (None, Some(b)) => b.range.start,
+            (None, None) => break,
+        };
+
+        // Extend the region while a hunk of either side overlaps or touches it.
+        let (first_a, first_b) = (next_a, next_b);
+        let mut end = start;
+        loop {
+            if let Some(a) = hunks_a.get(next_a).filter(|a| a.range.start <= end) {
+                end = end.max(a.range.end);
+                next_a += 1;
+            } else if let Some(b) = hunks_b.get(next_b).filter(|b| b.range.start <= end) {
+                end = end.max(b.range.end);
+                next_b += 1;
+            } else {
+                break;
+            }
+        }
+
+        if index < start {
+            regions.push(Region::Unchanged(base[index..start].to_vec()));
+        }
+
+        let base_ids = base[start..end].to_vec();
+        let a = apply(&base, start..end, &hunks_a[first_a..next_a]);
+        let b = apply(&base, start..end, &hunks_b[first_b..next_b]);
+        regions.push(if first_a == next_a {
+            Region::ChangedB(base_ids, b)
+        } else if first_b == next_b {
+            Region::ChangedA(base_ids, a)
+        } else if a == b {
+            Region::ChangedBoth(base_ids, a)
+        } else {
+            Region::Conflict(base_ids, a, b)
+        });
+        index = end;
+    }
+
+    if index < base.len() {
+        regions.push(Region::Unchanged(base[index..].to_vec()));
+    }
+
+    regions
+}
+
+/// Matches A and B against the base with `greedy00` and classifies the
+/// changed regions.
+pub fn greedy00_three_way(base: &[TokenId], a: &[TokenId], b: &[TokenId]) -> Vec<Region> {
+    greedy00_three_way_interruptible(base, a, b, Interrupt::none()).expect("not cancellable")
+}
+
+/// `greedy00_three_way`, reporting progress through the base for each match.
+pub fn greedy00_three_way_interruptible(
+    base: &[TokenId],
+    a: &[TokenId],
+    b: &[TokenId],
+    interrupt: Interrupt<'_>,
+) -> Result<Vec<Region>, Cancelled> {
+    let base_a = greedy00_interruptible(base, a, interrupt)?;
+    let base_b = greedy00_interruptible(base, b, interrupt)?;
+    Ok(three_way(&base_a, &base_b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[u32]) -> Vec<TokenId> {
+        ids.iter().copied().map(TokenId).collect()
+    }
+
+    #[test]
+    fn test_three_way_unchanged() {
+        let base_a = [Matched::Same(ids(&[0, 1, 2]))];
+        assert_eq!(
+            three_way(&base_a, &base_a),
+            [Region::Unchanged(ids(&[0, 1, 2]))]
+        );
+        assert_eq!(three_way(&[], &[]), []);
+    }
+
+    #[test]
+    fn test_three_way_separate_changes() {
+        let base_a = [
+            Matched::Diff(ids(&[0]), ids(&[5])),
+            Matched::Same(ids(&[1, 2, 3])),
+        ];
+        let base_b = [
+            Matched::Same(ids(&[0, 1, 2])),
+            Matched::Diff(ids(&[3]), ids(&[6, 7])),
+        ];
+        assert_eq!(
+            three_way(&base_a, &base_b),
+            [
+                Region::ChangedA(ids(&[0]), ids(&[5])),
+                Region::Unchanged(ids(&[1, 2])),
+                Region::ChangedB(ids(&[3]), ids(&[6, 7])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_three_way_overlapping_changes() {
+        let base = ids(&[0, 1, 2, 3, 4]);
+
+        // The same change on both sides isn't a conflict.
+        let base_a = [
+            Matched::Same(ids(&[0])),
+            Matched::Diff(ids(&[1, 2]), ids(&[7])),
+            Matched::Same(ids(&[3, 4])),
+        ];
+        assert_eq!(
+            three_way(&base_a, &base_a),
+            [
+                Region::Unchanged(ids(&[0])),
+                Region::ChangedBoth(ids(&[1, 2]), ids(&[7])),
+                Region::Unchanged(ids(&[3, 4])),
+            ]
+        );
+
+        // Overlapping hunks of A and B are combined into one region.
+        let base_b = [
+            Matched::Same(ids(&[0, 1])),
+            Matched::Diff(ids(&[2, 3]), ids(&[8])),
+            Matched::Same(ids(&[4])),
+        ];
+        assert_eq!(
+            three_way(&base_a, &base_b),
+            [
+                Region::Unchanged(ids(&[0])),
+                Region::Conflict(ids(&[1, 2, 3]), ids(&[7, 3]), ids(&[1, 8])),
+                Region::Unchanged(ids(&[4])),
+            ]
+        );
+
+        // Touching changes, and insertions at the same index, conflict.
+        let base_b = [
+            Matched::Same(ids(&[0, 1, 2])),
+            Matched::Diff(ids(&[]), ids(&[9])),
+            Matched::Same(ids(&[3, 4])),
+        ];
+        assert_eq!(
+            three_way(&base_a, &base_b),
+            [
+                Region::Unchanged(ids(&[0])),
+                Region::Conflict(ids(&[1, 2]), ids(&[7]), ids(&[1, 2, 9])),
+                Region::Unchanged(ids(&[3, 4])),
+            ]
+        );
+        let base_a = [
+            Matched::Same(ids(&[0, 1, 2])),
+            Matched::Diff(ids(&[]), ids(&[5])),
+            Matched::Same(ids(&[3, 4])),
+        ];
+        assert_eq!(
+            three_way(&base_a, &base_b),
+            [
+                Region::Unchanged(ids(&[0, 1, 2])),
+                Region::Conflict(ids(&[]), ids(&[5]), ids(&[9])),
+                Region::Unchanged(ids(&[3, 4])),
+            ]
+        );
+
+        let merged_base: Vec<TokenId> = three_way(&base_a, &base_b)
+            .into_iter()
+            .flat_map(|region| match region {
+                Region::Unchanged(ids)
+                | Region::ChangedA(ids, _)
+                | Region::ChangedB(ids, _)
+                | Region::ChangedBoth(ids, _)
+                | Region::Conflict(ids, _, _) => ids,
+            })
+            .collect();
+        assert_eq!(merged_base, base);
+    }
+
+    #[test]
+    #[should_panic(expected = "matches must be against the same base")]
+    fn test_three_way_different_bases() {
+        three_way(&[Matched::Same(ids(&[0]))], &[Matched::Same(ids(&[1]))]);
+    }
+
+    #[test]
+    fn test_greedy00_three_way() {
+        let base = ids(&[0, 1, 2, 3, 4, 5, 6]);
+        let a = ids(&[0, 1, 7, 3, 4, 5, 6]);
+        let b = ids(&[0, 1, 2, 3, 4, 8, 6]);
+        assert_eq!(
+            greedy00_three_way(&base, &a, &b),
+            [
+                Region::Unchanged(ids(&[0, 1])),
+                Region::ChangedA(ids(&[2]), ids(&[7])),
+                Region::Unchanged(ids(&[3, 4])),
+                Region::ChangedB(ids(&[5]), ids(&[8])),
+                Region::Unchanged(ids(&[6])),
+            ]
+        );
+    }
+}
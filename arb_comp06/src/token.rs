@@ -1,10 +1,41 @@
+use crate::interrupt::{Cancelled, Interrupt};
 use crate::pairs::ToPairs;
 use crate::utils::{add_to_counts, increment};
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
+/// Identifies a token of a vocabulary.
+///
+/// Ids are `u32`s to halve the memory of encoded patterns on 64-bit targets,
+/// so a vocabulary holds at most `u32::MAX` tokens: `TokenId::RESERVED` is
+/// never the id of a token.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub struct TokenId(pub usize);
+pub struct TokenId(pub u32);
+
+impl TokenId {
+    /// Not the id of any token, free for use as a marker.
+    pub const RESERVED: TokenId = TokenId(u32::MAX);
+
+    /// The id of the token at `index` in a vocabulary.
+    ///
+    /// Panics if `index` is out of the `TokenId` range or is `RESERVED`'s.
+    pub fn from_index(index: usize) -> Self {
+        let id = u32::try_from(index).expect("vocabulary exceeds the TokenId range");
+        assert_ne!(id, Self::RESERVED.0, "vocabulary exceeds the TokenId range");
+        Self(id)
+    }
+
+    /// The index of this token in its vocabulary.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl From<u8> for TokenId {
+    fn from(byte: u8) -> Self {
+        Self(byte.into())
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Token {
@@ -47,31 +78,57 @@ fn count_id_pairs(ids: &[TokenId]) -> IndexMap<(TokenId, TokenId), usize> {
 pub fn find_most_common_duplicate_id_pair<'a>(
     patterns: impl IntoIterator<Item = &'a Vec<TokenId>>,
 ) -> Option<((TokenId, TokenId), usize)> {
+    find_most_common_duplicate_id_pair_and_count(patterns).map(|(pair, count, _)| (pair, count))
+}
+
+/// Like `find_most_common_duplicate_id_pair`, but also returns the number of
+/// distinct pairs that occur more than once.
+pub fn find_most_common_duplicate_id_pair_and_count<'a>(
+    patterns: impl IntoIterator<Item = &'a Vec<TokenId>>,
+) -> Option<((TokenId, TokenId), usize, usize)> {
     let mut counts = IndexMap::new();
 
     for ids in patterns {
         add_to_counts(&mut counts, &count_id_pairs(ids));
     }
 
+    let duplicates = counts.values().filter(|&&count| count > 1).count();
+
     counts
         .into_iter()
         .max_by_key(|&(_, count)| count)
         .filter(|&(_, count)| count > 1)
+        .map(|(pair, count)| (pair, count, duplicates))
 }
 
 pub fn merge(
     iter: impl Iterator<Item = TokenId>,
     merge_if: impl Fn(TokenId, TokenId) -> Option<TokenId>,
 ) -> Vec<TokenId> {
+    merge_interruptible(iter, merge_if, 0, Interrupt::none()).expect("not cancellable")
+}
+
+/// `merge`, reporting the number of `TokenIds` consumed out of `total`.
+pub fn merge_interruptible(
+    iter: impl Iterator<Item = TokenId>,
+    merge_if: impl Fn(TokenId, TokenId) -> Option<TokenId>,
+    total: usize,
+    interrupt: Interrupt<'_>,
+) -> Result<Vec<TokenId>, Cancelled> {
     let mut result = vec![];
+    let mut done = 0;
 
     let mut pairs = iter.pairs();
     while let Some((id0, id1)) = pairs.next() {
+        interrupt.check(result.len(), done, total)?;
+
         if let Some(merged) = merge_if(id0, id1) {
             result.push(merged);
             pairs.next();
+            done += 2;
         } else {
             result.push(id0);
+            done += 1;
         }
     }
 
@@ -79,13 +136,41 @@ pub fn merge(
         result.push(id);
     }
 
-    result
+    Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_token_id() {
+        assert_eq!(std::mem::size_of::<TokenId>(), 4);
+        assert_eq!(TokenId::from_index(300), TokenId(300));
+        assert_eq!(TokenId(300).index(), 300);
+        assert_eq!(TokenId::from(255), TokenId(255));
+
+        // Serialized as a plain number, as when ids were `usize`s.
+        let ids = vec![TokenId(0), TokenId(u32::MAX)];
+        let json = serde_json::to_string(&ids).unwrap();
+        assert_eq!(json, "[0,4294967295]");
+        assert_eq!(serde_json::from_str::<Vec<TokenId>>(&json).unwrap(), ids);
+        assert!(serde_json::from_str::<TokenId>("4294967296").is_err());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    #[should_panic(expected = "vocabulary exceeds the TokenId range")]
+    fn test_token_id_overflow() {
+        TokenId::from_index(u32::MAX as usize + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "vocabulary exceeds the TokenId range")]
+    fn test_token_id_reserved() {
+        TokenId::from_index(TokenId::RESERVED.index());
+    }
+
     #[test]
     fn test_count_id_pairs() {
         let pattern = vec![];
@@ -196,6 +281,21 @@ mod tests {
         assert_eq!(result, Some(((TokenId(1), TokenId(2)), 2)));
     }
 
+    #[test]
+    fn test_find_most_common_duplicate_id_pair_and_count() {
+        let patterns = vec![vec![TokenId(0), TokenId(1), TokenId(2)]];
+        let result = find_most_common_duplicate_id_pair_and_count(&patterns);
+        assert_eq!(result, None);
+
+        let patterns = vec![
+            vec![TokenId(0), TokenId(1), TokenId(2)],
+            vec![TokenId(1), TokenId(2)],
+            vec![TokenId(0), TokenId(1), TokenId(2), TokenId(3)],
+        ];
+        let result = find_most_common_duplicate_id_pair_and_count(&patterns);
+        assert_eq!(result, Some(((TokenId(1), TokenId(2)), 3, 2)));
+    }
+
     #[test]
     fn test_merge() {
         let merge_tester = |pattern: &[TokenId], id0: TokenId, id1: TokenId, merged: TokenId| {
@@ -209,7 +309,7 @@ mod tests {
             merge(pattern.iter().copied(), f)
         };
 
-        let result = merge_tester(&vec![], TokenId(0), TokenId(1), TokenId(3));
+        let result = merge_tester(&[], TokenId(0), TokenId(1), TokenId(3));
         assert_eq!(result, vec![]);
 
         let pattern = vec![TokenId(0), TokenId(1), TokenId(2), TokenId(3)];
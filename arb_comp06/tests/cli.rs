@@ -0,0 +1,30 @@
+//! Runs the command line tool as a user would.
+
+use std::process::{Command, Output};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_arb_comp06"))
+        .args(args)
+        .output()
+        .expect("failed to run arb_comp06")
+}
+
+/// Asserts that `cluster` with `args` fails with a usage error naming `error`.
+fn assert_cluster_usage_error(args: &[&str], error: &str) {
+    let output = run(&[&["cluster"], args].concat());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{args:?}: {stderr}");
+    assert!(stderr.contains(error), "{args:?}: {stderr}");
+    assert!(stderr.contains("usage: cluster"), "{args:?}: {stderr}");
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_cluster_usage_errors() {
+    assert_cluster_usage_error(&["--linkage", "median", "a", "b"], "Unknown linkage");
+    assert_cluster_usage_error(&["--linkage"], "Unknown linkage");
+    assert_cluster_usage_error(&["--metric", "cosine", "a", "b"], "Unknown metric");
+    assert_cluster_usage_error(&["--frobnicate", "a"], "Unknown option");
+    assert_cluster_usage_error(&[], "No files to cluster");
+    assert_cluster_usage_error(&["--json"], "No files to cluster");
+}
@@ -0,0 +1,227 @@
+//! Where the byte of a hex cell is: its offset in each file, and the token
+//! and block it is part of.
+
+use crate::diff::HexCell;
+use crate::token_inspector::{self, TokenInfo};
+use egui::Ui;
+use std::mem::discriminant;
+use std::ops::Range;
+
+/// The cells of one file shown in the table.
+pub struct FileColumn<'a> {
+    pub name: &'a str,
+    pub cells: &'a [HexCell],
+    /// `row_offsets` of `cells`.
+    pub row_offsets: &'a [usize],
+}
+
+/// Offset in its file of the first byte of every row of `width` cells.
+/// Blank cells are padding, not bytes of the file, so they aren't counted.
+pub fn row_offsets(cells: &[HexCell], width: usize) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(cells.len().div_ceil(width));
+    let mut offset = 0;
+    for row in cells.chunks(width) {
+        offsets.push(offset);
+        offset += row.iter().filter(|cell| **cell != HexCell::Blank).count();
+    }
+    offsets
+}
+
+/// Offset in its file of the byte of cell `index` of `column`, shown in rows
+/// of `width` cells. None if the cell is blank or past the end.
+pub fn file_offset(column: &FileColumn<'_>, width: usize, index: usize) -> Option<usize> {
+    column.cells.get(index)?.source()?;
+    let row_start = index - index % width;
+    let in_row = column.cells[row_start..index]
+        .iter()
+        .filter(|cell| **cell != HexCell::Blank)
+        .count();
+    Some(column.row_offsets[index / width] + in_row)
+}
+
+/// Index of the cell of byte `offset` of the file of `column`, shown in rows
+/// of `width` cells: the inverse of `file_offset`. None if past the end.
+pub fn cell_index(column: &FileColumn<'_>, width: usize, offset: usize) -> Option<usize> {
+    // Rows without bytes start at the offset of the next row with bytes.
+    let row = column
+        .row_offsets
+        .partition_point(|&start| start <= offset)
+        .checked_sub(1)?;
+    let row_start = row * width;
+    let row_end = (row_start + width).min(column.cells.len());
+    (row_start..row_end)
+        .filter(|&index| column.cells[index] != HexCell::Blank)
+        .nth(offset - column.row_offsets[row])
+}
+
+/// Cell indices of the run of same, different or conflicting cells that
+/// contains `cells[index]`, or None if the cell is blank or past the end.
+pub fn block(cells: &[HexCell], index: usize) -> Option<Range<usize>> {
+    cells.get(index)?.source()?;
+    let kind = discriminant(&cells[index]);
+    let start = cells[..index]
+        .iter()
+        .rposition(|cell| discriminant(cell) != kind)
+        .map_or(0, |i| i + 1);
+    let end = cells[index..]
+        .iter()
+        .position(|cell| discriminant(cell) != kind)
+        .map_or(cells.len(), |i| index + i);
+    Some(start..end)
+}
+
+/// `value` in hex, decimal, binary, and as a character if printable.
+pub fn value_text(value: u8) -> String {
+    let char = char::from(value);
+    let char = if char.is_ascii_graphic() || char == ' ' {
+        format!("{char:?}")
+    } else {
+        "not printable".to_string()
+    };
+    format!("0x{value:02X}  {value}  0b{value:08b}  {char}")
+}
+
+/// Shows everything known about cell `index` of `columns[column]`: its
+/// value, offset in every shown file, its block and its source token.
+/// Rows are `width` cells long. If `unaligned`, cells with the same index in
+/// other columns are unrelated, so only the offset in this file is shown.
+pub fn show(
+    ui: &mut Ui,
+    columns: &[FileColumn<'_>],
+    width: usize,
+    column: usize,
+    index: usize,
+    unaligned: bool,
+    vocabulary: &[TokenInfo],
+) {
+    let shown = if unaligned {
+        &columns[column..column + 1]
+    } else {
+        columns
+    };
+    let cells = columns[column].cells;
+    let cell = cells[index];
+    let (HexCell::Same { value, .. }
+    | HexCell::Diff { value, .. }
+    | HexCell::Conflict { value, .. }) = cell
+    else {
+        return;
+    };
+    ui.monospace(value_text(value));
+
+    for column in shown {
+        let name = column.name;
+        match file_offset(column, width, index) {
+            Some(offset) => ui.label(format!("{name} offset 0x{offset:X} ({offset})")),
+            None => ui.label(format!("{name}: no byte here")),
+        };
+    }
+
+    if let Some(block) = block(cells, index) {
+        let kind = match cell {
+            HexCell::Same { .. } => "matching",
+            HexCell::Diff { .. } => "differing",
+            _ => "conflicting",
+        };
+        ui.label(format!(
+            "{kind} block of {} bytes, cells {}..{}",
+            block.len(),
+            block.start,
+            block.end
+        ));
+    }
+
+    let Some((source_id, provenance)) = cell.source() else {
+        return;
+    };
+    let Some(info) = vocabulary.get(source_id) else {
+        ui.label("compared by index");
+        return;
+    };
+    ui.label(format!(
+        "token {source_id}: byte {} of {}, merge depth {}",
+        provenance.offset + 1,
+        info.bytes.len(),
+        info.depth
+    ));
+    let [count0, count1, count_base] = info.counts;
+    if count_base > 0 {
+        ui.label(format!(
+            "used {count0} times in file 0, {count1} in file 1, {count_base} in the base"
+        ));
+    } else {
+        ui.label(format!("used {count0} times in file 0, {count1} in file 1"));
+    }
+    ui.monospace(token_inspector::hex_text(&info.bytes));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::Provenance;
+
+    fn same(value: u8) -> HexCell {
+        HexCell::Same {
+            value,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        }
+    }
+
+    fn diff(value: u8) -> HexCell {
+        HexCell::Diff {
+            value,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        }
+    }
+
+    #[test]
+    fn test_offsets_and_blocks() {
+        let cells = [same(1), diff(2), HexCell::Blank, diff(3), same(4)];
+
+        let row_offsets = row_offsets(&cells, 2);
+        assert_eq!(row_offsets, [0, 2, 3]);
+        let column = FileColumn {
+            name: "file 0",
+            cells: &cells,
+            row_offsets: &row_offsets,
+        };
+        assert_eq!(file_offset(&column, 2, 0), Some(0));
+        assert_eq!(file_offset(&column, 2, 2), None, "blank cells aren't bytes");
+        assert_eq!(file_offset(&column, 2, 3), Some(2));
+        assert_eq!(file_offset(&column, 2, 4), Some(3));
+        assert_eq!(file_offset(&column, 2, 5), None);
+
+        assert_eq!(cell_index(&column, 2, 0), Some(0));
+        assert_eq!(cell_index(&column, 2, 2), Some(3));
+        assert_eq!(cell_index(&column, 2, 3), Some(4));
+        assert_eq!(cell_index(&column, 2, 4), None);
+
+        let padded = [HexCell::Blank, HexCell::Blank, same(1), same(2)];
+        let padded_offsets = super::row_offsets(&padded, 2);
+        let padded_column = FileColumn {
+            name: "file 1",
+            cells: &padded,
+            row_offsets: &padded_offsets,
+        };
+        assert_eq!(
+            cell_index(&padded_column, 2, 0),
+            Some(2),
+            "skips blank rows"
+        );
+
+        assert_eq!(block(&cells, 0), Some(0..1));
+        assert_eq!(block(&cells, 1), Some(1..2), "blank cells end a block");
+        assert_eq!(block(&cells, 3), Some(3..4));
+        assert_eq!(block(&cells, 4), Some(4..5));
+        assert_eq!(block(&cells, 2), None);
+        assert_eq!(block(&[diff(0), diff(1), diff(2)], 1), Some(0..3));
+    }
+
+    #[test]
+    fn test_value_text() {
+        assert_eq!(value_text(b'A'), "0x41  65  0b01000001  'A'");
+        assert_eq!(value_text(0), "0x00  0  0b00000000  not printable");
+    }
+}
@@ -0,0 +1,774 @@
+//! The diff job lifecycle, independent of the UI.
+//!
+//! `DiffJobs` starts, cancels and supersedes diff jobs, collects their
+//! progress and holds their published results. It only needs a callback to
+//! wake the UI, so it can be driven headless.
+
+use crate::diff;
+use crate::hex_app::DiffMethod;
+use crate::jobs::{Job, JobRunner, JobState, Task};
+use crate::progress::{Phase, Progress, ProgressMessage};
+use crate::snapshots::{DiffResults, Inputs, Published};
+use crate::token_inspector;
+use arb_comp06::{
+    bpe::{Bpe, MergeInfo},
+    chunked::{self, DEFAULT_CHUNK_SIZE, DEFAULT_SAMPLE_SIZE},
+    input::Input,
+    interrupt::{Cancelled, Interrupt},
+    matcher, n_way, similarity, techniques, test_utils, three_way,
+    token::TokenId,
+};
+use std::sync::Arc;
+
+/// Trains a `Bpe` on `patterns`, calling `on_merge` after each merge with
+/// the encoded length of each of `patterns`.
+pub fn train(
+    patterns: &[&[u8]],
+    job: &Job,
+    on_merge: impl Fn(&MergeInfo),
+) -> Result<Bpe, Cancelled> {
+    log::debug!("starting new_iterative");
+    // Large inputs are trained on a sample, of several chunks each.
+    let training = chunked::training_sample(patterns, DEFAULT_CHUNK_SIZE, DEFAULT_SAMPLE_SIZE);
+    let owners = chunked::sample_owners(patterns, DEFAULT_CHUNK_SIZE, DEFAULT_SAMPLE_SIZE);
+    let on_merge = |info: &MergeInfo| {
+        on_merge(&MergeInfo {
+            pattern_lengths: chunked::lengths_per_input(
+                &info.pattern_lengths,
+                &owners,
+                patterns.len(),
+            ),
+            ..info.clone()
+        })
+    };
+    let mut bpe = Bpe::new_iterative(&training);
+    log::debug!("finished new_iterative");
+    while bpe.init_in_progress.is_some() {
+        bpe.init_step(Some(&on_merge));
+
+        if job.is_cancelled() {
+            return Err(Cancelled);
+        }
+    }
+    Ok(bpe)
+}
+
+/// Trains a `Bpe` on `patterns` and encodes each of them with it, in chunks
+/// like the command line tool.
+fn train_and_encode<const N: usize>(
+    patterns: [&[u8]; N],
+    job: &Job,
+    send: &dyn Fn(ProgressMessage),
+    interrupt: Interrupt<'_>,
+) -> Result<(Bpe, [Vec<TokenId>; N]), Cancelled> {
+    send(ProgressMessage::Phase(Phase::Training));
+    let bpe = train(&patterns, job, |info| {
+        send(ProgressMessage::Merge(info.clone()))
+    })?;
+
+    send(ProgressMessage::Phase(Phase::Encoding));
+    let mut encoded = Vec::with_capacity(N);
+    for pattern in patterns {
+        encoded.push(chunked::encode_interruptible(
+            &bpe,
+            pattern,
+            DEFAULT_CHUNK_SIZE,
+            interrupt,
+        )?);
+    }
+    let encoded = encoded.try_into().expect("one encoding per pattern");
+    Ok((bpe, encoded))
+}
+
+/// Trains a `Bpe` on both patterns and diffs their encodings with `greedy00`.
+fn bpe_greedy00_diffs(
+    pattern0: &[u8],
+    pattern1: &[u8],
+    job: &Job,
+    send: &dyn Fn(ProgressMessage),
+    interrupt: Interrupt<'_>,
+) -> Result<DiffResults, Cancelled> {
+    let (bpe, [pattern0, pattern1]) = train_and_encode([pattern0, pattern1], job, send, interrupt)?;
+
+    send(ProgressMessage::Phase(Phase::Matching));
+    let matches = matcher::greedy00_interruptible(&pattern0, &pattern1, interrupt)?;
+
+    send(ProgressMessage::Phase(Phase::Rendering));
+    let (diffs0, diffs1) =
+        test_utils::matches_to_cells_interruptible(&matches, |x| bpe.decode(x.clone()), interrupt)?;
+    let vocabulary = token_inspector::vocabulary(&bpe, [&pattern0, &pattern1, &[]]);
+    let similarity = similarity::similarity(&matches, &n_way::token_lengths(&bpe));
+    Ok(DiffResults {
+        diffs0,
+        diffs1,
+        vocabulary,
+        similarity: Some(similarity),
+        ..Default::default()
+    })
+}
+
+/// Trains a `Bpe` on both patterns and shows each in terms of the other with
+/// `techniques::in_terms_of`.
+fn bpe_in_terms_of_diffs(
+    pattern0: &[u8],
+    pattern1: &[u8],
+    job: &Job,
+    send: &dyn Fn(ProgressMessage),
+    interrupt: Interrupt<'_>,
+) -> Result<DiffResults, Cancelled> {
+    let (bpe, [pattern0, pattern1]) = train_and_encode([pattern0, pattern1], job, send, interrupt)?;
+
+    send(ProgressMessage::Phase(Phase::Matching));
+    let segments0 = techniques::in_terms_of_interruptible(&bpe, &pattern0, &pattern1, interrupt)?;
+    let segments1 = techniques::in_terms_of_interruptible(&bpe, &pattern1, &pattern0, interrupt)?;
+
+    send(ProgressMessage::Phase(Phase::Rendering));
+    let decode = |x: &Vec<_>| bpe.decode(x.clone());
+    let diffs0 = test_utils::segments_to_cells_interruptible(&segments0, decode, interrupt)?;
+    let diffs1 = test_utils::segments_to_cells_interruptible(&segments1, decode, interrupt)?;
+    let vocabulary = token_inspector::vocabulary(&bpe, [&pattern0, &pattern1, &[]]);
+    Ok(DiffResults {
+        diffs0,
+        diffs1,
+        vocabulary,
+        unaligned: true,
+        ..Default::default()
+    })
+}
+
+/// Trains a `Bpe` on all three patterns and shows pattern 0 and pattern 1 in
+/// terms of the base, next to the base's own tokens.
+fn bpe_in_terms_of_three_way_diffs(
+    base: &[u8],
+    pattern0: &[u8],
+    pattern1: &[u8],
+    job: &Job,
+    send: &dyn Fn(ProgressMessage),
+    interrupt: Interrupt<'_>,
+) -> Result<DiffResults, Cancelled> {
+    let (bpe, [base, pattern0, pattern1]) =
+        train_and_encode([base, pattern0, pattern1], job, send, interrupt)?;
+
+    send(ProgressMessage::Phase(Phase::Matching));
+    let segments0 = techniques::in_terms_of_interruptible(&bpe, &pattern0, &base, interrupt)?;
+    let segments1 = techniques::in_terms_of_interruptible(&bpe, &pattern1, &base, interrupt)?;
+
+    send(ProgressMessage::Phase(Phase::Rendering));
+    let vocabulary = token_inspector::vocabulary(&bpe, [&pattern0, &pattern1, &base]);
+    let decode = |x: &Vec<_>| bpe.decode(x.clone());
+    let base_segment = techniques::Segment {
+        ids: base,
+        in_other: true,
+    };
+    let diffs_base =
+        test_utils::segments_to_cells_interruptible(&[base_segment], decode, interrupt)?;
+    let diffs0 = test_utils::segments_to_cells_interruptible(&segments0, decode, interrupt)?;
+    let diffs1 = test_utils::segments_to_cells_interruptible(&segments1, decode, interrupt)?;
+    Ok(DiffResults {
+        diffs_base,
+        diffs0,
+        diffs1,
+        vocabulary,
+        unaligned: true,
+        ..Default::default()
+    })
+}
+
+/// Trains a `Bpe` on all three patterns and classifies the changes of
+/// pattern 0 and pattern 1 against the base with `greedy00_three_way`.
+fn bpe_greedy00_three_way_diffs(
+    base: &[u8],
+    pattern0: &[u8],
+    pattern1: &[u8],
+    job: &Job,
+    send: &dyn Fn(ProgressMessage),
+    interrupt: Interrupt<'_>,
+) -> Result<DiffResults, Cancelled> {
+    let (bpe, [base, pattern0, pattern1]) =
+        train_and_encode([base, pattern0, pattern1], job, send, interrupt)?;
+
+    send(ProgressMessage::Phase(Phase::Matching));
+    let regions =
+        three_way::greedy00_three_way_interruptible(&base, &pattern0, &pattern1, interrupt)?;
+
+    send(ProgressMessage::Phase(Phase::Rendering));
+    let (diffs_base, diffs0, diffs1) =
+        test_utils::regions_to_cells_interruptible(&regions, |x| bpe.decode(x.clone()), interrupt)?;
+    let vocabulary = token_inspector::vocabulary(&bpe, [&pattern0, &pattern1, &base]);
+    Ok(DiffResults {
+        diffs_base,
+        diffs0,
+        diffs1,
+        vocabulary,
+        ..Default::default()
+    })
+}
+
+/// Computes the diff of `inputs`, then publishes it to `results` if `job` is
+/// still current.
+fn run(inputs: Inputs, job: &Job, results: &Published, send: &dyn Fn(ProgressMessage)) {
+    if job.is_cancelled() {
+        send(ProgressMessage::Phase(Phase::Cancelled));
+        return;
+    }
+
+    let report = |done, total| send(ProgressMessage::Step { done, total });
+    let interrupt = Interrupt::new(Some(job.cancel_token()), Some(&report));
+    let result = match (&inputs.base, &inputs.pattern0, &inputs.pattern1) {
+        (None, Some(pattern0), Some(pattern1)) => {
+            let len = std::cmp::max(pattern0.len(), pattern1.len());
+            match inputs.diff_method {
+                DiffMethod::ByIndex => {
+                    send(ProgressMessage::Phase(Phase::Matching));
+                    let (diffs0, diffs1) = diff::get_diffs(pattern0, pattern1, 0..len);
+                    Ok(DiffResults {
+                        diffs0,
+                        diffs1,
+                        ..Default::default()
+                    })
+                }
+                DiffMethod::BpeGreedy00 => {
+                    bpe_greedy00_diffs(pattern0, pattern1, job, send, interrupt)
+                }
+                DiffMethod::BpeInTermsOf => {
+                    bpe_in_terms_of_diffs(pattern0, pattern1, job, send, interrupt)
+                }
+            }
+        }
+        (Some(base), Some(pattern0), Some(pattern1)) => {
+            let len = base.len().max(pattern0.len()).max(pattern1.len());
+            match inputs.diff_method {
+                DiffMethod::ByIndex => {
+                    send(ProgressMessage::Phase(Phase::Matching));
+                    let (diffs_base, diffs0, diffs1) =
+                        diff::get_diffs3(base, pattern0, pattern1, 0..len);
+                    Ok(DiffResults {
+                        diffs_base,
+                        diffs0,
+                        diffs1,
+                        ..Default::default()
+                    })
+                }
+                DiffMethod::BpeGreedy00 => {
+                    bpe_greedy00_three_way_diffs(base, pattern0, pattern1, job, send, interrupt)
+                }
+                DiffMethod::BpeInTermsOf => {
+                    bpe_in_terms_of_three_way_diffs(base, pattern0, pattern1, job, send, interrupt)
+                }
+            }
+        }
+        _ => Ok(DiffResults::default()),
+    };
+    let Ok(result) = result else {
+        send(ProgressMessage::Phase(Phase::Cancelled));
+        return;
+    };
+
+    let published = job.is_current()
+        && results.publish(DiffResults {
+            generation: inputs.generation,
+            files: [inputs.pattern0, inputs.pattern1, inputs.base],
+            ..result
+        });
+
+    if published {
+        log::info!("published results of job {}", job.id);
+        send(ProgressMessage::Phase(Phase::Done));
+    } else {
+        log::info!("discarding results of superseded job {}", job.id);
+        send(ProgressMessage::Phase(Phase::Cancelled));
+    }
+}
+
+/// Runs diff jobs for the UI, one at a time.
+///
+/// Call `poll` once per frame: it starts a queued job when the previous one
+/// has stopped and collects progress messages.
+pub struct DiffJobs {
+    runner: JobRunner<Inputs, ProgressMessage>,
+    results: Arc<Published>,
+    progress: Progress,
+}
+
+impl DiffJobs {
+    /// Runs jobs on the rayon thread pool. Workers call `request_repaint`
+    /// after every progress message and when they stop.
+    pub fn new(request_repaint: impl Fn() + Send + Sync + 'static) -> Self {
+        Self::with_spawner(request_repaint, rayon::spawn)
+    }
+
+    /// Like `new`, but runs jobs with `spawn`.
+    pub fn with_spawner(
+        request_repaint: impl Fn() + Send + Sync + 'static,
+        spawn: impl Fn(Task) + 'static,
+    ) -> Self {
+        let results = Arc::new(Published::default());
+        let published = results.clone();
+        let work = move |mut inputs: Inputs, job: &Job, send: &dyn Fn(ProgressMessage)| {
+            inputs.generation = job.id;
+            run(inputs, job, &published, send);
+        };
+        Self {
+            runner: JobRunner::new(work, request_repaint, spawn),
+            results,
+            progress: Progress::new(),
+        }
+    }
+
+    /// Requests a diff of the given inputs, cancelling any running job.
+    pub fn request(
+        &mut self,
+        pattern0: Option<Arc<Input>>,
+        pattern1: Option<Arc<Input>>,
+        diff_method: DiffMethod,
+    ) {
+        self.request_three_way(None, pattern0, pattern1, diff_method);
+    }
+
+    /// Like `request`, but diffs both patterns against `base` if it is Some.
+    pub fn request_three_way(
+        &mut self,
+        base: Option<Arc<Input>>,
+        pattern0: Option<Arc<Input>>,
+        pattern1: Option<Arc<Input>>,
+        diff_method: DiffMethod,
+    ) {
+        self.runner.request(Inputs {
+            generation: 0,
+            base,
+            pattern0,
+            pattern1,
+            diff_method,
+        });
+        self.poll();
+    }
+
+    /// Cancels the running job and drops any queued request.
+    pub fn cancel(&mut self) {
+        self.runner.cancel();
+    }
+
+    /// Starts the queued job if no job is running, and collects progress.
+    pub fn poll(&mut self) {
+        if self.runner.start_queued().is_some() {
+            self.progress.start();
+        }
+        for message in self.runner.messages() {
+            self.progress.update(message);
+        }
+    }
+
+    /// The most recently published results.
+    pub fn results(&self) -> Arc<DiffResults> {
+        self.results.load()
+    }
+
+    /// Progress of the current (or most recent) job.
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    pub fn state(&self) -> JobState {
+        self.runner.state()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.runner.is_running()
+    }
+
+    /// Publishes `results` as if a job had computed them.
+    #[cfg(test)]
+    pub fn publish(&self, results: DiffResults) -> bool {
+        self.results.publish(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::JobManager;
+    use arb_comp06::diff::HexCell;
+    use std::collections::VecDeque;
+    use std::sync::{mpsc, Mutex};
+    use std::thread::{self, JoinHandle};
+
+    /// Queues spawned jobs until the test runs them.
+    #[derive(Clone, Default)]
+    struct ManualSpawner(Arc<Mutex<VecDeque<Task>>>);
+
+    impl ManualSpawner {
+        fn spawner(&self) -> impl Fn(Task) + 'static {
+            let queue = self.0.clone();
+            move |task| queue.lock().unwrap().push_back(task)
+        }
+
+        fn queued(&self) -> usize {
+            self.0.lock().unwrap().len()
+        }
+
+        fn take_next(&self) -> Task {
+            self.0
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("no job was spawned")
+        }
+
+        fn run_next(&self) {
+            self.take_next()();
+        }
+
+        fn run_next_in_thread(&self) -> JoinHandle<()> {
+            thread::spawn(self.take_next())
+        }
+    }
+
+    /// Repaint callback that pauses the worker at every repaint until the test
+    /// resumes it. Dropping the gate lets the worker run freely.
+    struct Gate {
+        paused_rx: mpsc::Receiver<()>,
+        resume_tx: mpsc::Sender<()>,
+    }
+
+    impl Gate {
+        fn new() -> (Self, impl Fn() + Send + Sync + 'static) {
+            let (paused_tx, paused_rx) = mpsc::channel();
+            let (resume_tx, resume_rx) = mpsc::channel();
+            let (paused_tx, resume_rx) = (Mutex::new(paused_tx), Mutex::new(resume_rx));
+            let repaint = move || {
+                paused_tx.lock().unwrap().send(()).ok();
+                resume_rx.lock().unwrap().recv().ok();
+            };
+            (
+                Self {
+                    paused_rx,
+                    resume_tx,
+                },
+                repaint,
+            )
+        }
+
+        /// Waits until the worker pauses. Messages sent before pausing can be polled.
+        fn wait(&self) {
+            self.paused_rx.recv().unwrap();
+        }
+
+        fn resume(&self) {
+            self.resume_tx.send(()).unwrap();
+        }
+    }
+
+    fn pattern(bytes: &[u8]) -> Option<Arc<Input>> {
+        Some(Arc::new(Input::from(bytes.to_vec())))
+    }
+
+    fn repetitive_pattern() -> Option<Arc<Input>> {
+        pattern(&b"abcdefgh".repeat(64))
+    }
+
+    #[test]
+    fn test_run_to_completion() {
+        let spawner = ManualSpawner::default();
+        let mut jobs = DiffJobs::with_spawner(|| {}, spawner.spawner());
+
+        jobs.request(pattern(b"abc"), pattern(b"abd"), DiffMethod::ByIndex);
+        assert_eq!(jobs.state(), JobState::Running(1));
+        assert_eq!(spawner.queued(), 1);
+        assert_eq!(jobs.results().generation, 0);
+
+        spawner.run_next();
+        jobs.poll();
+        assert_eq!(jobs.state(), JobState::Idle);
+        assert!(!jobs.progress().is_running());
+
+        let results = jobs.results();
+        assert_eq!(results.generation, 1);
+        assert_eq!(
+            results.diffs1,
+            diff::get_diffs(b"abc", b"abd", 0..3).1,
+            "diffs of the requested inputs"
+        );
+    }
+
+    #[test]
+    fn test_cancel_before_start() {
+        let spawner = ManualSpawner::default();
+        let mut jobs = DiffJobs::with_spawner(|| {}, spawner.spawner());
+
+        jobs.request(pattern(b"abc"), pattern(b"abd"), DiffMethod::BpeGreedy00);
+        jobs.cancel();
+        assert_eq!(
+            jobs.state(),
+            JobState::Cancelling {
+                id: 1,
+                queued: false
+            }
+        );
+
+        spawner.run_next();
+        jobs.poll();
+        assert_eq!(jobs.state(), JobState::Idle);
+        assert_eq!(spawner.queued(), 0, "nothing restarts after a cancel");
+        assert!(jobs.progress().merges().is_empty(), "training never ran");
+        assert!(!jobs.progress().is_running());
+        assert_eq!(jobs.results().generation, 0, "nothing was published");
+    }
+
+    #[test]
+    fn test_cancel_mid_training() {
+        let spawner = ManualSpawner::default();
+        let (gate, repaint) = Gate::new();
+        let mut jobs = DiffJobs::with_spawner(repaint, spawner.spawner());
+
+        jobs.request(
+            repetitive_pattern(),
+            repetitive_pattern(),
+            DiffMethod::BpeGreedy00,
+        );
+        let worker = spawner.run_next_in_thread();
+
+        // Step the worker until it reports its first merge.
+        loop {
+            gate.wait();
+            jobs.poll();
+            if !jobs.progress().merges().is_empty() {
+                break;
+            }
+            gate.resume();
+        }
+        jobs.cancel();
+        drop(gate);
+        worker.join().unwrap();
+
+        jobs.poll();
+        assert_eq!(jobs.state(), JobState::Idle);
+        assert_eq!(
+            jobs.progress().merges().len(),
+            1,
+            "training stops after the merge in progress"
+        );
+        assert!(!jobs.progress().is_running());
+        assert_eq!(jobs.results().generation, 0, "nothing was published");
+    }
+
+    #[test]
+    fn test_train_reports_lengths_per_input() {
+        let mut manager = JobManager::new();
+        manager.request();
+        let job = manager.poll().unwrap();
+
+        // Trained on several chunks of the large input, one of the small.
+        let large = b"ab".repeat(DEFAULT_SAMPLE_SIZE / 2 + 1);
+        let merges = Mutex::new(vec![]);
+        let trained = train(&[&large, b"abab"], &job, |info| {
+            merges.lock().unwrap().push(info.pattern_lengths.clone());
+            job.cancel_token().cancel();
+        });
+        assert!(trained.is_err());
+        assert_eq!(*merges.lock().unwrap(), [vec![DEFAULT_SAMPLE_SIZE / 2, 2]]);
+    }
+
+    #[test]
+    fn test_rapid_retriggers() {
+        let spawner = ManualSpawner::default();
+        let mut jobs = DiffJobs::with_spawner(|| {}, spawner.spawner());
+
+        jobs.request(pattern(b"a"), pattern(b"a"), DiffMethod::BpeGreedy00);
+        jobs.request(pattern(b"b"), pattern(b"b"), DiffMethod::BpeGreedy00);
+        jobs.request(pattern(b"c"), pattern(b"d"), DiffMethod::ByIndex);
+        assert_eq!(spawner.queued(), 1, "one job runs at a time");
+        assert_eq!(
+            jobs.state(),
+            JobState::Cancelling {
+                id: 1,
+                queued: true
+            }
+        );
+
+        spawner.run_next();
+        jobs.poll();
+        assert_eq!(spawner.queued(), 1, "only the latest request starts");
+        assert_eq!(jobs.state(), JobState::Running(3));
+        assert_eq!(jobs.results().generation, 0);
+
+        spawner.run_next();
+        jobs.poll();
+        assert_eq!(jobs.state(), JobState::Idle);
+        assert_eq!(spawner.queued(), 0);
+        let results = jobs.results();
+        assert_eq!(results.generation, 3);
+        assert_eq!(results.diffs0, diff::get_diffs(b"c", b"d", 0..1).0);
+    }
+
+    #[test]
+    fn test_superseded_result_is_discarded() {
+        let spawner = ManualSpawner::default();
+        let (gate, repaint) = Gate::new();
+        let mut jobs = DiffJobs::with_spawner(repaint, spawner.spawner());
+
+        jobs.request(pattern(b"old"), pattern(b"old"), DiffMethod::ByIndex);
+        let worker = spawner.run_next_in_thread();
+
+        // ByIndex doesn't check for cancellation: the old job computes its
+        // result after being superseded, and must not publish it.
+        gate.wait();
+        jobs.request(pattern(b"new"), pattern(b"new"), DiffMethod::ByIndex);
+        drop(gate);
+        worker.join().unwrap();
+        assert_eq!(jobs.results().generation, 0);
+
+        jobs.poll();
+        assert_eq!(jobs.state(), JobState::Running(2));
+        spawner.run_next();
+        jobs.poll();
+
+        let results = jobs.results();
+        assert_eq!(results.generation, 2);
+        assert_eq!(results.diffs0, diff::get_diffs(b"new", b"new", 0..3).0);
+    }
+
+    #[test]
+    fn test_similarity() {
+        let spawner = ManualSpawner::default();
+        let mut jobs = DiffJobs::with_spawner(|| {}, spawner.spawner());
+
+        jobs.request(
+            repetitive_pattern(),
+            repetitive_pattern(),
+            DiffMethod::BpeGreedy00,
+        );
+        spawner.run_next();
+        jobs.poll();
+        let similarity = jobs.results().similarity.expect("scored by a BPE diff");
+        assert_eq!(similarity.same_fraction, 1.0);
+        assert_eq!(similarity.jaccard, 1.0);
+        assert!((0.0..=1.0).contains(&similarity.ncd), "{similarity:?}");
+
+        jobs.request(pattern(b"abc"), pattern(b"abd"), DiffMethod::ByIndex);
+        spawner.run_next();
+        jobs.poll();
+        assert_eq!(jobs.results().similarity, None);
+    }
+
+    #[test]
+    fn test_in_terms_of() {
+        let spawner = ManualSpawner::default();
+        let mut jobs = DiffJobs::with_spawner(|| {}, spawner.spawner());
+
+        jobs.request(
+            pattern(b"Hello 12345 World"),
+            pattern(b"Hello World"),
+            DiffMethod::BpeInTermsOf,
+        );
+        spawner.run_next();
+        jobs.poll();
+        let results = jobs.results();
+        let shown = |cells: &[HexCell], diff: bool| -> Vec<u8> {
+            cells
+                .iter()
+                .filter_map(|cell| match *cell {
+                    HexCell::Same { value, .. } if !diff => Some(value),
+                    HexCell::Diff { value, .. } if diff => Some(value),
+                    _ => None,
+                })
+                .collect()
+        };
+        let (same0, diff0) = (shown(&results.diffs0, false), shown(&results.diffs0, true));
+        assert_eq!(same0.len() + diff0.len(), b"Hello 12345 World".len());
+        assert!(
+            diff0.starts_with(b"12345"),
+            "{:?}",
+            String::from_utf8_lossy(&diff0)
+        );
+        assert_eq!(shown(&results.diffs1, false), b"Hello World");
+
+        jobs.request_three_way(
+            pattern(b"Hello World"),
+            pattern(b"Hello 12345 World"),
+            pattern(b"Hello World"),
+            DiffMethod::BpeInTermsOf,
+        );
+        spawner.run_next();
+        jobs.poll();
+        let results = jobs.results();
+        assert_eq!(shown(&results.diffs_base, false), b"Hello World");
+        assert!(shown(&results.diffs0, true).starts_with(b"12345"));
+        assert_eq!(shown(&results.diffs1, false), b"Hello World");
+    }
+
+    #[test]
+    fn test_three_way() {
+        let spawner = ManualSpawner::default();
+        let mut jobs = DiffJobs::with_spawner(|| {}, spawner.spawner());
+
+        jobs.request_three_way(
+            pattern(b"abcd"),
+            pattern(b"aXcd"),
+            pattern(b"abcY"),
+            DiffMethod::ByIndex,
+        );
+        spawner.run_next();
+        jobs.poll();
+        let results = jobs.results();
+        let (diffs_base, diffs0, diffs1) = diff::get_diffs3(b"abcd", b"aXcd", b"abcY", 0..4);
+        assert_eq!(results.diffs_base, diffs_base);
+        assert_eq!(results.diffs0, diffs0);
+        assert_eq!(results.diffs1, diffs1);
+
+        jobs.request_three_way(
+            pattern(b"abcdefgh"),
+            pattern(b"abXdefgh"),
+            pattern(b"abcdefYh"),
+            DiffMethod::BpeGreedy00,
+        );
+        spawner.run_next();
+        jobs.poll();
+        let results = jobs.results();
+        assert_eq!(results.generation, 2);
+        assert_eq!(results.diffs_base.len(), results.diffs0.len());
+        assert_eq!(results.diffs_base.len(), results.diffs1.len());
+        let values = |cells: &[HexCell]| -> Vec<u8> {
+            cells
+                .iter()
+                .filter_map(|cell| match *cell {
+                    HexCell::Same { value, .. }
+                    | HexCell::Diff { value, .. }
+                    | HexCell::Conflict { value, .. } => Some(value),
+                    HexCell::Blank => None,
+                })
+                .collect()
+        };
+        assert_eq!(values(&results.diffs_base), b"abcdefgh");
+        assert_eq!(values(&results.diffs0), b"abXdefgh");
+        assert_eq!(values(&results.diffs1), b"abcdefYh");
+        let base_bytes: usize = results
+            .vocabulary
+            .iter()
+            .map(|info| info.counts[2] * info.bytes.len())
+            .sum();
+        assert_eq!(base_bytes, b"abcdefgh".len(), "the base is counted");
+
+        // A two-way request clears the base.
+        jobs.request(pattern(b"a"), pattern(b"b"), DiffMethod::ByIndex);
+        spawner.run_next();
+        jobs.poll();
+        assert!(jobs.results().diffs_base.is_empty());
+    }
+
+    #[test]
+    fn test_publish_ordering() {
+        let results = Published::default();
+        let publish = |generation| {
+            results.publish(DiffResults {
+                generation,
+                ..Default::default()
+            })
+        };
+
+        assert!(publish(2));
+        assert!(!publish(1), "older results never replace newer ones");
+        assert_eq!(results.load().generation, 2);
+        assert!(publish(3));
+        assert_eq!(results.load().generation, 3);
+    }
+}
@@ -0,0 +1,206 @@
+//! N-way comparison of the file set: one vocabulary is trained on all of its
+//! files, and every pair is matched to score its similarity.
+
+use crate::diff_jobs;
+use crate::jobs::{Job, JobRunner, Task};
+use arb_comp06::{
+    chunked::{self, DEFAULT_CHUNK_SIZE},
+    input::Input,
+    interrupt::{Cancelled, Interrupt},
+    n_way::{self, SimilarityMatrix},
+};
+use egui::{Color32, RichText, Ui};
+use std::sync::Arc;
+
+/// Message sent from a similarity job worker to the UI.
+enum Message {
+    /// Pairs matched so far.
+    Step {
+        done: usize,
+        total: usize,
+    },
+    Done(SimilarityMatrix),
+}
+
+fn similarity(
+    files: &[Arc<Input>],
+    job: &Job,
+    send: &dyn Fn(Message),
+) -> Result<SimilarityMatrix, Cancelled> {
+    let report = |done, total| send(Message::Step { done, total });
+    let interrupt = Interrupt::new(Some(job.cancel_token()), Some(&report));
+
+    let data: Vec<&[u8]> = files.iter().map(|file| &file[..]).collect();
+    let bpe = diff_jobs::train(&data, job, |_| {})?;
+
+    let encoded = data
+        .iter()
+        .map(|data| {
+            chunked::encode_interruptible(
+                &bpe,
+                data,
+                DEFAULT_CHUNK_SIZE,
+                interrupt.without_progress(),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    n_way::similarity_matrix_interruptible(&encoded, &n_way::token_lengths(&bpe), interrupt)
+}
+
+/// Computes the similarity matrix of `files`, and sends it if `job` is still
+/// current.
+fn run(files: Vec<Arc<Input>>, job: &Job, send: &dyn Fn(Message)) {
+    if job.is_cancelled() {
+        return;
+    }
+    if let Ok(matrix) = similarity(&files, job, send) {
+        if job.is_current() {
+            send(Message::Done(matrix));
+        }
+    }
+}
+
+/// Computes the similarity matrix of the file set, one job at a time.
+///
+/// Call `poll` once per frame to start a queued job and collect its result.
+pub struct SimilarityJobs {
+    runner: JobRunner<Vec<Arc<Input>>, Message>,
+    /// Pairs matched by the running job.
+    step: Option<(usize, usize)>,
+    matrix: Option<SimilarityMatrix>,
+}
+
+impl SimilarityJobs {
+    /// Runs jobs on the rayon thread pool.
+    pub fn new(request_repaint: impl Fn() + Send + Sync + 'static) -> Self {
+        Self::with_spawner(request_repaint, rayon::spawn)
+    }
+
+    /// Like `new`, but runs jobs with `spawn`.
+    pub fn with_spawner(
+        request_repaint: impl Fn() + Send + Sync + 'static,
+        spawn: impl Fn(Task) + 'static,
+    ) -> Self {
+        Self {
+            runner: JobRunner::new(run, request_repaint, spawn),
+            step: None,
+            matrix: None,
+        }
+    }
+
+    /// Requests the similarity matrix of `files`, cancelling any running job
+    /// and dropping the previous matrix.
+    pub fn request(&mut self, files: Vec<Arc<Input>>) {
+        self.runner.request(files);
+        self.matrix = None;
+        self.poll();
+    }
+
+    /// Cancels any running job and drops the matrix.
+    pub fn clear(&mut self) {
+        self.runner.cancel();
+        self.matrix = None;
+    }
+
+    /// Starts the queued job if no job is running, and collects its result.
+    pub fn poll(&mut self) {
+        if self.runner.start_queued().is_some() {
+            self.step = Some((0, 0));
+        }
+        for message in self.runner.messages() {
+            match message {
+                Message::Step { done, total } => self.step = Some((done, total)),
+                Message::Done(matrix) => self.matrix = Some(matrix),
+            }
+        }
+        if !self.runner.is_running() {
+            self.step = None;
+        }
+    }
+
+    /// The matrix of the latest request, once computed.
+    pub fn matrix(&self) -> Option<&SimilarityMatrix> {
+        self.matrix.as_ref()
+    }
+
+    /// Pairs matched and total pairs, while a job is running.
+    pub fn step(&self) -> Option<(usize, usize)> {
+        self.step
+    }
+}
+
+/// Shows `matrix` as a grid of scores, returning the pair whose score was clicked.
+pub fn show_matrix(
+    ui: &mut Ui,
+    names: &[&str],
+    matrix: &SimilarityMatrix,
+) -> Option<(usize, usize)> {
+    let mut clicked = None;
+    egui::Grid::new("similarity_matrix")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("");
+            for (j, name) in names.iter().enumerate() {
+                ui.label(RichText::new(j.to_string()).strong())
+                    .on_hover_text(*name);
+            }
+            ui.end_row();
+
+            for (i, row) in matrix.iter().enumerate() {
+                ui.label(RichText::new(i.to_string()).strong())
+                    .on_hover_text(names[i]);
+                for (j, &score) in row.iter().enumerate() {
+                    // Darker red for less similar pairs.
+                    let shade = (255.0 * score) as u8;
+                    let text = RichText::new(format!("{:.1}", score * 100.0))
+                        .monospace()
+                        .color(Color32::BLACK)
+                        .background_color(Color32::from_rgb(255, shade, shade));
+                    if i == j {
+                        ui.label(text);
+                    } else if ui
+                        .button(text)
+                        .on_hover_text(format!("diff {} and {}", names[i], names[j]))
+                        .clicked()
+                    {
+                        clicked = Some((i, j));
+                    }
+                }
+                ui.label(names[i]);
+                ui.end_row();
+            }
+        });
+    clicked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(bytes: &[u8]) -> Arc<Input> {
+        Arc::new(Input::from(bytes.to_vec()))
+    }
+
+    #[test]
+    fn test_similarity_jobs() {
+        let mut jobs = SimilarityJobs::with_spawner(|| {}, |task| task());
+        let files = vec![
+            input(b"abcdabcdabcd"),
+            input(b"abcdabcdabce"),
+            input(b"xyzxyzxyzxyz"),
+        ];
+
+        jobs.request(files.clone());
+        jobs.poll();
+        assert_eq!(jobs.step(), None, "the job has finished");
+
+        let data: Vec<&[u8]> = files.iter().map(|file| &file[..]).collect();
+        let (bpe, encoded) = n_way::encode_all(&data);
+        let expected = n_way::similarity_matrix(&encoded, &n_way::token_lengths(&bpe));
+        assert_eq!(jobs.matrix(), Some(&expected));
+
+        jobs.request(files[..2].to_vec());
+        jobs.poll();
+        assert_eq!(jobs.matrix().map(Vec::len), Some(2));
+    }
+}
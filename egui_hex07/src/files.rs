@@ -0,0 +1,152 @@
+//! Opening input files: the browser file picker and the recent files list.
+
+use arb_comp06::input::Input;
+use egui::DroppedFile;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const MAX_RECENT_FILES: usize = 10;
+
+/// Most recently opened files, newest first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecentFiles(Vec<PathBuf>);
+
+impl RecentFiles {
+    /// Key of the list in `eframe` storage.
+    pub const STORAGE_KEY: &'static str = "recent_files";
+
+    /// Moves `path` to the front of the list, dropping the oldest entry if full.
+    pub fn add(&mut self, path: PathBuf) {
+        self.0.retain(|recent| *recent != path);
+        self.0.insert(0, path);
+        self.0.truncate(MAX_RECENT_FILES);
+    }
+
+    // Browsers don't expose file paths, so the list stays empty on the web.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.0
+    }
+}
+
+/// A file read into memory.
+#[derive(Debug, Clone)]
+pub struct LoadedFile {
+    pub name: String,
+    /// Where the file was read from, if it came from the file system.
+    pub path: Option<PathBuf>,
+    pub data: Arc<Input>,
+}
+
+impl LoadedFile {
+    /// Reads the file at `path`. It isn't mapped, since the files the app
+    /// watches can be rewritten while it shows them.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            name: path.to_string_lossy().to_string(),
+            path: Some(path.to_path_buf()),
+            data: Arc::new(Input::read(path)?),
+        })
+    }
+}
+
+/// Reads dropped files, returning the files that could be read and a message
+/// for each file that couldn't.
+pub fn read_dropped_files(dropped: &[DroppedFile]) -> (Vec<LoadedFile>, Vec<String>) {
+    let mut loaded = vec![];
+    let mut rejected = vec![];
+    for file in dropped {
+        // `path` should only be Some when running as a native app,
+        // `bytes` only when running as a web app.
+        if let Some(path) = &file.path {
+            match LoadedFile::open(path) {
+                Ok(file) => loaded.push(file),
+                Err(e) => rejected.push(format!("{}: {e}", path.display())),
+            }
+        } else if let Some(bytes) = &file.bytes {
+            loaded.push(LoadedFile {
+                name: file.name.clone(),
+                path: None,
+                data: Arc::new(Input::from(bytes.to_vec())),
+            });
+        } else {
+            rejected.push(format!("{}: no file contents", file.name));
+        }
+    }
+    (loaded, rejected)
+}
+
+/// Shows the browser file picker, then calls `on_open` with the name and
+/// contents of the picked file.
+#[cfg(target_arch = "wasm32")]
+pub fn pick_file(on_open: impl FnOnce(String, Vec<u8>) + 'static) {
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Some(file) = rfd::AsyncFileDialog::new().pick_file().await {
+            let bytes = file.read().await;
+            on_open(file.file_name(), bytes);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_files() {
+        let mut recent = RecentFiles::default();
+        recent.add("a".into());
+        recent.add("b".into());
+        recent.add("a".into());
+        assert_eq!(recent.paths(), [PathBuf::from("a"), PathBuf::from("b")]);
+
+        for i in 0..MAX_RECENT_FILES {
+            recent.add(i.to_string().into());
+        }
+        assert_eq!(recent.paths().len(), MAX_RECENT_FILES);
+        assert_eq!(recent.paths()[0], PathBuf::from("9"));
+        assert!(!recent.paths().contains(&PathBuf::from("a")));
+    }
+
+    #[test]
+    fn test_read_dropped_files() {
+        let dir = std::env::temp_dir().join(format!("egui_hex07_drop_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let native = dir.join("native.bin");
+        std::fs::write(&native, b"abc").unwrap();
+
+        let dropped = [
+            DroppedFile {
+                path: Some(native.clone()),
+                ..Default::default()
+            },
+            DroppedFile {
+                path: Some(dir.join("missing.bin")),
+                ..Default::default()
+            },
+            DroppedFile {
+                name: "web.bin".to_string(),
+                bytes: Some(Arc::from(&b"de"[..])),
+                ..Default::default()
+            },
+            DroppedFile {
+                name: "empty".to_string(),
+                ..Default::default()
+            },
+        ];
+        let (loaded, rejected) = read_dropped_files(&dropped);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].path, Some(native));
+        assert_eq!(loaded[0].data[..], b"abc"[..]);
+        assert_eq!(loaded[1].name, "web.bin");
+        assert_eq!(loaded[1].path, None);
+        assert_eq!(loaded[1].data[..], b"de"[..]);
+
+        assert_eq!(rejected.len(), 2);
+        assert!(rejected[0].contains("missing.bin"), "{rejected:?}");
+        assert_eq!(rejected[1], "empty: no file contents");
+    }
+}
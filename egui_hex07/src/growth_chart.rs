@@ -0,0 +1,50 @@
+use arb_comp06::bpe::MergeInfo;
+use egui::Ui;
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+
+fn line(merges: &[MergeInfo], y: impl Fn(&MergeInfo) -> Option<usize>) -> PlotPoints<'static> {
+    merges
+        .iter()
+        .enumerate()
+        .filter_map(|(step, info)| y(info).map(|y| [step as f64, y as f64]))
+        .collect()
+}
+
+/// Plots, per training step, the frequency of the merged pair and the
+/// encoded length of each input after the merge, labelled with `names`.
+pub fn show(ui: &mut Ui, merges: &[MergeInfo], names: &[&str]) {
+    if merges.is_empty() {
+        ui.label("no merges yet (run a BPE diff method)");
+        return;
+    }
+
+    let height = (ui.available_height() / 2.0 - ui.spacing().item_spacing.y).max(100.0);
+
+    Plot::new("merge_frequency")
+        .legend(Legend::default())
+        .height(height)
+        .x_axis_label("step")
+        .link_axis("growth_chart", [true, false])
+        .show(ui, |plot_ui| {
+            plot_ui
+                .line(Line::new(line(merges, |info| Some(info.pair_count))).name("pair frequency"));
+        });
+
+    Plot::new("encoded_lengths")
+        .legend(Legend::default())
+        .height(height)
+        .x_axis_label("step")
+        .link_axis("growth_chart", [true, false])
+        .show(ui, |plot_ui| {
+            let inputs = merges[0].pattern_lengths.len();
+            for i in 0..inputs {
+                let name = names
+                    .get(i)
+                    .map_or(format!("input {i}"), |name| name.to_string());
+                plot_ui.line(
+                    Line::new(line(merges, |info| info.pattern_lengths.get(i).copied()))
+                        .name(format!("{name} length")),
+                );
+            }
+        });
+}
@@ -1,18 +1,77 @@
-use crate::diff::{self, HexCell};
-use arb_comp06::{bpe::Bpe, matcher, test_utils};
-use egui::{Color32, Context, RichText, Ui};
+use crate::cell_info::{self, FileColumn};
+use crate::diff::{HexCell, Provenance};
+use crate::diff_jobs::DiffJobs;
+use crate::file_set::{self, SimilarityJobs};
+use crate::files::{self, LoadedFile, RecentFiles};
+use crate::growth_chart;
+use crate::jobs::JobState;
+use crate::merge_tree;
+use crate::minimap::Minimap;
+use crate::navigation;
+use crate::search::{self, Hits, Query, SearchJobs, SearchKind};
+use crate::snapshots::DiffResults;
+use crate::token_inspector;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::watcher::FileWatcher;
+use arb_comp06::{input::Input, token::TokenId};
+use egui::{Color32, Key, KeyboardShortcut, Label, Modifiers, RichText, Sense, TextEdit, Ui};
 use egui_extras::{Column, TableBody, TableBuilder, TableRow};
 use rand::Rng;
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    mpsc, Arc, Mutex,
-};
+use regex::bytes::Regex;
+use std::cell::Cell;
+use std::ops::Range;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 enum WhichFile {
     File0,
     File1,
+    /// The common base of a three-way diff.
+    Base,
 }
+
+impl WhichFile {
+    fn name(self) -> &'static str {
+        match self {
+            WhichFile::File0 => "file 0",
+            WhichFile::File1 => "file 1",
+            WhichFile::Base => "base",
+        }
+    }
+
+    /// Index of the file in arrays of all three.
+    fn index(self) -> usize {
+        match self {
+            WhichFile::File0 => 0,
+            WhichFile::File1 => 1,
+            WhichFile::Base => 2,
+        }
+    }
+
+    /// The aligned cells of the file in `results`.
+    fn cells(self, results: &DiffResults) -> &[HexCell] {
+        match self {
+            WhichFile::File0 => &results.diffs0,
+            WhichFile::File1 => &results.diffs1,
+            WhichFile::Base => &results.diffs_base,
+        }
+    }
+}
+
+/// Names of the `inputs` files a diff job trains on, in order. A three-way
+/// diff trains on the base first, as the table shows it.
+fn training_names(inputs: usize) -> Vec<&'static str> {
+    let files: &[WhichFile] = if inputs == 3 {
+        &[WhichFile::Base, WhichFile::File0, WhichFile::File1]
+    } else {
+        &[WhichFile::File0, WhichFile::File1]
+    };
+    files.iter().map(|side| side.name()).collect()
+}
+
 fn drop_select_text(selected: bool) -> &'static str {
     if selected {
         "⬇ Loading dropped files here ⬇"
@@ -21,27 +80,139 @@ fn drop_select_text(selected: bool) -> &'static str {
     }
 }
 
+/// What the color of a cell shows.
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum ColorMode {
+    /// A color per token id.
+    Token,
+    /// The first byte of each token in a different color.
+    Boundary,
+    /// Depth of the token's merge tree.
+    Depth,
+    /// Occurrences of the token in both files.
+    Frequency,
+}
+
+/// A message shown above the table until dismissed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Message {
+    /// Something that was asked for failed.
+    Error(String),
+    /// Something worth knowing about that went as asked.
+    Notice(String),
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
-enum DiffMethod {
+pub enum DiffMethod {
     ByIndex,
     BpeGreedy00,
+    /// Each file in terms of the other's tokens, with `techniques::in_terms_of`.
+    BpeInTermsOf,
 }
 
 pub struct HexApp {
     source_name0: Option<String>,
     source_name1: Option<String>,
-    pattern0: Arc<Mutex<Option<Vec<u8>>>>,
-    pattern1: Arc<Mutex<Option<Vec<u8>>>>,
-    diffs0: Arc<Mutex<Vec<HexCell>>>,
-    diffs1: Arc<Mutex<Vec<HexCell>>>,
+    source_name_base: Option<String>,
+    pattern0: Option<Arc<Input>>,
+    pattern1: Option<Arc<Input>>,
+    pattern_base: Option<Arc<Input>>,
+    /// Canonical paths the patterns were read from, if read from files.
+    #[cfg(not(target_arch = "wasm32"))]
+    source_path0: Option<PathBuf>,
+    #[cfg(not(target_arch = "wasm32"))]
+    source_path1: Option<PathBuf>,
+    #[cfg(not(target_arch = "wasm32"))]
+    source_path_base: Option<PathBuf>,
+    /// Diffs file 0 (A) and file 1 (B) against the base.
+    three_way: bool,
+    /// Reloads changed files when watching is enabled.
+    #[cfg(not(target_arch = "wasm32"))]
+    watcher: Option<FileWatcher>,
+    shown_generation: u64,
+    /// Keeps token selections when the next results are published, because
+    /// they come from reloading the same files.
+    preserve_selection: bool,
+    selected_token: Option<TokenId>,
+    /// Index of the clicked cell. It is highlighted in every file, unless
+    /// the results are unaligned.
+    selected_cell: Option<usize>,
+    /// Column of the table the selected cell was picked in, if any. Only
+    /// this column's cell is highlighted when the results are unaligned.
+    selected_column: Option<usize>,
+    /// `cell_info::row_offsets` of file 0, file 1 and the base, and the
+    /// generation of the results they were computed from.
+    row_offsets: Option<(u64, Arc<[Vec<usize>; 3]>)>,
+    minimap: Option<(u64, bool, Arc<Minimap>)>,
+    /// Row the table scrolls to in the next frame.
+    scroll_to_row: Option<usize>,
+    /// Rows of the table drawn in the last frame.
+    visible_rows: Range<usize>,
+    /// Offset typed in the go-to box, in hex, and the file it is in.
+    go_to_text: String,
+    go_to_file: WhichFile,
+    search_text: String,
+    search_kind: SearchKind,
+    /// The last search run and its regex, found again in each generation.
+    search_query: Option<(Query, Regex)>,
+    /// Finds `search_query` in the shown files.
+    searches: SearchJobs,
+    /// Goes to the next hit, or the previous one if false, once the running
+    /// search has found them.
+    pending_hit: Option<bool>,
+    show_token_inspector: bool,
+    show_growth_chart: bool,
+    merge_tree_token: Option<TokenId>,
     file_drop_target: WhichFile,
     diff_method: DiffMethod,
-    update_new_id_rx: Option<mpsc::Receiver<usize>>,
-    egui_context: Context,
-    job_running: Arc<AtomicBool>,
-    cancel_job: Arc<AtomicBool>,
+    color_mode: ColorMode,
+    jobs: DiffJobs,
+    recent_files: RecentFiles,
+    /// Files dropped together for multi-way comparison.
+    file_set: Vec<LoadedFile>,
+    /// Computes the pairwise similarity of the file set.
+    similarity: SimilarityJobs,
+    show_file_set: bool,
+    messages: Vec<Message>,
+    /// Files picked in the browser, received once they have been read.
+    #[cfg(target_arch = "wasm32")]
+    picked_files: (
+        std::sync::mpsc::Sender<PickedFile>,
+        std::sync::mpsc::Receiver<PickedFile>,
+    ),
 }
 
+/// A file picked in the browser: the side it's for, its name and its bytes.
+#[cfg(target_arch = "wasm32")]
+type PickedFile = (WhichFile, String, Vec<u8>);
+
+/// Files larger than this are loaded with a notice that the GUI isn't made
+/// for them: it holds a table cell in memory for every byte.
+const LARGE_FILE_SIZE: usize = 64 << 20;
+
+/// Background of bytes changed differently in A and B of a three-way diff.
+const CONFLICT_COLOR: Color32 = Color32::from_rgb(192, 0, 0);
+
+/// Cells per row of the hex table.
+const HEX_GRID_WIDTH: usize = 16;
+
+/// Width of the minimap beside the table.
+const MINIMAP_WIDTH: f32 = 32.0;
+
+/// Moves to the next difference, and with shift to the previous one.
+const NEXT_DIFF_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::F8);
+const PREVIOUS_DIFF_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::SHIFT, Key::F8);
+
+/// Moves to the next search hit, and with shift to the previous one.
+const NEXT_HIT_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::F3);
+const PREVIOUS_HIT_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::SHIFT, Key::F3);
+
+/// Background of the bytes matching the search, in every file.
+const SEARCH_HIT_COLOR: Color32 = Color32::from_rgb(255, 150, 0);
+
+/// Background of the selected cell, in every file.
+const SELECTED_CELL_COLOR: Color32 = Color32::from_rgb(255, 220, 0);
+
 fn random_pattern() -> Vec<u8> {
     let mut rng = rand::thread_rng();
     (0..4000).map(|_| rng.gen_range(0..=255)).collect()
@@ -49,194 +220,741 @@ fn random_pattern() -> Vec<u8> {
 
 impl HexApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let mut result = Self {
+        Self::with_files(cc, &[])
+    }
+
+    /// Like `new`, but loads the files at `paths` into file 0 and file 1.
+    /// More than two files also become the file set.
+    pub fn with_files(cc: &eframe::CreationContext<'_>, paths: &[PathBuf]) -> Self {
+        #[cfg(target_arch = "wasm32")]
+        // Spawn an async task to request egui repaints from the main thread.
+        // (When attempted from a Web Worker thread, the program panics.)
+        let request_repaint = {
+            use futures::{channel::mpsc, StreamExt as _};
+
+            let (tx, mut rx) = mpsc::unbounded::<()>();
+            let egui_context = cc.egui_ctx.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                while let Some(()) = rx.next().await {
+                    egui_context.request_repaint();
+                }
+                log::info!("loop ENDED");
+            });
+
+            move || {
+                tx.unbounded_send(()).ok();
+            }
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let request_repaint = {
+            let egui_context = cc.egui_ctx.clone();
+            move || egui_context.request_repaint()
+        };
+
+        let mut result = Self::with_jobs(
+            DiffJobs::new(request_repaint.clone()),
+            SimilarityJobs::new(request_repaint.clone()),
+            SearchJobs::new(request_repaint),
+        );
+        if let Some(storage) = cc.storage {
+            result.recent_files =
+                eframe::get_value(storage, RecentFiles::STORAGE_KEY).unwrap_or_default();
+        }
+
+        let mut files = vec![];
+        for path in paths {
+            match LoadedFile::open(path) {
+                Ok(file) => files.push(file),
+                Err(e) => result.show_error(format!("failed to read {}: {e}", path.display())),
+            }
+        }
+        if paths.len() > 2 {
+            result.set_file_set(files.clone());
+        }
+        for (side, file) in [WhichFile::File0, WhichFile::File1].into_iter().zip(files) {
+            result.open_loaded(side, file);
+        }
+
+        result.update_diffs();
+        result
+    }
+
+    /// Creates the app without starting a diff job.
+    fn with_jobs(jobs: DiffJobs, similarity: SimilarityJobs, searches: SearchJobs) -> Self {
+        Self {
             source_name0: Some("zeroes0".to_string()),
             source_name1: Some("zeroes1".to_string()),
-            pattern0: Arc::new(Mutex::new(Some(vec![0; 1000]))),
-            pattern1: Arc::new(Mutex::new(Some(vec![0; 1000]))),
-            diffs0: Arc::new(Mutex::new(vec![])),
-            diffs1: Arc::new(Mutex::new(vec![])),
+            pattern0: Some(Arc::new(Input::from(vec![0; 1000]))),
+            pattern1: Some(Arc::new(Input::from(vec![0; 1000]))),
+            source_name_base: None,
+            pattern_base: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            source_path0: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            source_path1: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            source_path_base: None,
+            three_way: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            watcher: None,
+            shown_generation: 0,
+            preserve_selection: false,
+            selected_token: None,
+            selected_cell: None,
+            selected_column: None,
+            row_offsets: None,
+            minimap: None,
+            scroll_to_row: None,
+            visible_rows: 0..0,
+            go_to_text: String::new(),
+            go_to_file: WhichFile::File0,
+            search_text: String::new(),
+            search_kind: SearchKind::Hex,
+            search_query: None,
+            searches,
+            pending_hit: None,
+            show_token_inspector: false,
+            show_growth_chart: false,
+            merge_tree_token: None,
             file_drop_target: WhichFile::File0,
             diff_method: DiffMethod::ByIndex,
-            update_new_id_rx: None,
-            egui_context: cc.egui_ctx.clone(),
-            job_running: Arc::new(AtomicBool::new(false)),
-            cancel_job: Arc::new(AtomicBool::new(false)),
-        };
+            color_mode: ColorMode::Token,
+            jobs,
+            recent_files: RecentFiles::default(),
+            file_set: vec![],
+            similarity,
+            show_file_set: false,
+            messages: vec![],
+            #[cfg(target_arch = "wasm32")]
+            picked_files: std::sync::mpsc::channel(),
+        }
+    }
 
-        result.update_diffs();
-        result
+    fn set_file(&mut self, side: WhichFile, name: String, pattern: Arc<Input>) {
+        if pattern.len() > LARGE_FILE_SIZE {
+            self.show_notice(format!(
+                "{name} is {} MiB: the table needs about 17 times that in memory. \
+                 The arb_comp06 command line tool diffs large files in chunks.",
+                pattern.len() >> 20
+            ));
+        }
+        self.set_pattern(side, pattern);
+        match side {
+            WhichFile::File0 => self.source_name0 = Some(name),
+            WhichFile::File1 => self.source_name1 = Some(name),
+            WhichFile::Base => self.source_name_base = Some(name),
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.set_source_path(side, None);
     }
 
-    fn try_set_pattern0(&mut self, pattern: Vec<u8>) -> bool {
-        if self.job_running.load(Ordering::Acquire) {
-            false
-        } else {
-            let mut pattern0 = self.pattern0.lock().unwrap();
-            *pattern0 = Some(pattern);
-            true
+    /// Replaces the contents of `side`, keeping its name and path.
+    fn set_pattern(&mut self, side: WhichFile, pattern: Arc<Input>) {
+        match side {
+            WhichFile::File0 => self.pattern0 = Some(pattern),
+            WhichFile::File1 => self.pattern1 = Some(pattern),
+            WhichFile::Base => self.pattern_base = Some(pattern),
         }
     }
-    fn try_set_pattern1(&mut self, pattern: Vec<u8>) -> bool {
-        if self.job_running.load(Ordering::Acquire) {
-            false
-        } else {
-            let mut pattern1 = self.pattern1.lock().unwrap();
-            *pattern1 = Some(pattern);
-            true
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_source_path(&mut self, side: WhichFile, path: Option<PathBuf>) {
+        match side {
+            WhichFile::File0 => self.source_path0 = path,
+            WhichFile::File1 => self.source_path1 = path,
+            WhichFile::Base => self.source_path_base = path,
         }
     }
 
-    fn update_diffs(&mut self) {
-        if self.job_running.load(Ordering::Acquire) {
+    fn show_error(&mut self, message: String) {
+        log::warn!("{message}");
+        self.messages.push(Message::Error(message));
+    }
+
+    fn show_notice(&mut self, message: String) {
+        log::info!("{message}");
+        self.messages.push(Message::Notice(message));
+    }
+
+    /// Loads `file` into `side`, adding its path to the recent files.
+    fn open_loaded(&mut self, side: WhichFile, file: LoadedFile) {
+        self.set_file(side, file.name, file.data);
+        if let Some(path) = &file.path {
+            let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+            self.recent_files.add(path.clone());
+            #[cfg(not(target_arch = "wasm32"))]
+            self.set_source_path(side, Some(path));
+        }
+    }
+
+    /// Starts or stops reloading files when they change on disk.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_watching(&mut self, watching: bool, ctx: &egui::Context) {
+        if !watching {
+            self.watcher = None;
             return;
         }
-        self.job_running.store(true, Ordering::Release);
 
-        let pattern0 = self.pattern0.clone();
-        let pattern1 = self.pattern1.clone();
+        let egui_context = ctx.clone();
+        match FileWatcher::new(move || egui_context.request_repaint()) {
+            Ok(watcher) => self.watcher = Some(watcher),
+            Err(e) => self.show_error(format!("failed to start watching files: {e}")),
+        }
+    }
 
-        let diffs0 = self.diffs0.clone();
-        let diffs1 = self.diffs1.clone();
+    /// Reloads files whose changes have settled, and re-runs the diff.
+    /// Scroll position and token selections are kept.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_changed_files(&mut self, ctx: &egui::Context) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
 
-        let diff_method = self.diff_method;
-        let egui_context = self.egui_context.clone();
+        let paths = [
+            &self.source_path0,
+            &self.source_path1,
+            &self.source_path_base,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+        let watch_result = watcher.set_paths(paths);
+        let changed = watcher.poll();
+        if let Some(wait) = watcher.next_poll() {
+            ctx.request_repaint_after(wait);
+        }
+        if let Err(e) = watch_result {
+            self.show_error(format!("failed to watch files: {e}"));
+        }
 
-        let (tx, rx) = mpsc::channel::<usize>();
-        self.update_new_id_rx = Some(rx);
+        let mut reloaded = false;
+        for (side, path) in [
+            (WhichFile::File0, self.source_path0.clone()),
+            (WhichFile::File1, self.source_path1.clone()),
+            (WhichFile::Base, self.source_path_base.clone()),
+        ] {
+            if let Some(path) = path.filter(|path| changed.contains(path)) {
+                self.reload(side, &path);
+                reloaded = true;
+            }
+        }
+        if reloaded {
+            self.update_diffs();
+            self.preserve_selection = true;
+        }
+    }
 
-        #[cfg(target_arch = "wasm32")]
-        // Spawn an async task to request egui repaints from the main thread.
-        // (When attempted from a Web Worker thread, the program panics.)
-        let refresh_egui_tx = {
-            use futures::{channel::mpsc, StreamExt as _};
+    /// Rereads the file of `side` from `path`, keeping its name, its path and
+    /// the recent files as they are.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload(&mut self, side: WhichFile, path: &Path) {
+        log::info!("reloading {}", path.display());
+        match Input::read(path) {
+            Ok(input) => self.set_pattern(side, Arc::new(input)),
+            Err(e) => self.show_error(format!("failed to reload {}: {e}", path.display())),
+        }
+    }
 
-            let (tx, mut rx) = mpsc::unbounded::<()>();
-            let egui_context = self.egui_context.clone();
+    /// Loads the file at `path` into `side`.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_path(&mut self, side: WhichFile, path: &Path) {
+        match LoadedFile::open(path) {
+            Ok(file) => self.open_loaded(side, file),
+            Err(e) => self.show_error(format!("failed to read {}: {e}", path.display())),
+        }
+    }
 
-            wasm_bindgen_futures::spawn_local(async move {
-                while let Some(()) = rx.next().await {
+    /// Replaces the file set, and starts comparing its files.
+    fn set_file_set(&mut self, files: Vec<LoadedFile>) {
+        if files.is_empty() {
+            self.similarity.clear();
+        } else {
+            self.similarity
+                .request(files.iter().map(|file| file.data.clone()).collect());
+        }
+        self.file_set = files;
+        self.show_file_set = !self.file_set.is_empty();
+    }
+
+    /// Diffs files `i` and `j` of the file set.
+    fn open_pair(&mut self, i: usize, j: usize) {
+        self.open_loaded(WhichFile::File0, self.file_set[i].clone());
+        self.open_loaded(WhichFile::File1, self.file_set[j].clone());
+        self.update_diffs();
+    }
+
+    /// Loads dropped files: a single file into the drop target, two files into
+    /// file 0 and file 1, replacing any file set. Three or more become the
+    /// file set, with the first two also loaded into file 0 and file 1, or in
+    /// a three-way diff the first three loaded into the base, file 0 and file 1.
+    fn open_dropped(&mut self, mut files: Vec<LoadedFile>) {
+        if (1..=2).contains(&files.len()) {
+            self.set_file_set(vec![]);
+        }
+        match files.len() {
+            0 => return,
+            1 => self.open_loaded(self.file_drop_target, files.remove(0)),
+            n => {
+                if n > 2 {
+                    self.show_notice(format!("loaded {n} files as a file set"));
+                    self.set_file_set(files.clone());
+                }
+                let mut files = files.into_iter();
+                if self.three_way && n > 2 {
+                    self.open_loaded(WhichFile::Base, files.next().unwrap());
+                }
+                self.open_loaded(WhichFile::File0, files.next().unwrap());
+                self.open_loaded(WhichFile::File1, files.next().unwrap());
+            }
+        }
+        self.update_diffs();
+    }
+
+    /// "Open…" and recent files buttons for `side`.
+    fn add_open_buttons(&mut self, ui: &mut Ui, side: WhichFile) {
+        if ui.button("Open…").clicked() {
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(path) = rfd::FileDialog::new().pick_file() {
+                self.open_path(side, &path);
+                self.update_diffs();
+            }
+
+            #[cfg(target_arch = "wasm32")]
+            {
+                let tx = self.picked_files.0.clone();
+                let egui_context = ui.ctx().clone();
+                files::pick_file(move |name, bytes| {
+                    tx.send((side, name, bytes)).ok();
                     egui_context.request_repaint();
+                });
+            }
+        }
+
+        // Browsers don't expose file paths, so there is nothing to reopen.
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.add_enabled_ui(!self.recent_files.paths().is_empty(), |ui| {
+            ui.menu_button("recent", |ui| {
+                let mut picked = None;
+                for path in self.recent_files.paths() {
+                    let name = path
+                        .file_name()
+                        .unwrap_or(path.as_os_str())
+                        .to_string_lossy();
+                    if ui
+                        .button(name)
+                        .on_hover_text(path.to_string_lossy())
+                        .clicked()
+                    {
+                        picked = Some(path.clone());
+                        ui.close_menu();
+                    }
+                }
+                if let Some(path) = picked {
+                    self.open_path(side, &path);
+                    self.update_diffs();
                 }
-                log::info!("loop ENDED");
-                // One more repaint after the last worker thread finishes.
-                egui_context.request_repaint();
             });
+        });
+    }
+
+    /// Requests a new diff job, cancelling any running one.
+    fn update_diffs(&mut self) {
+        self.preserve_selection = false;
+        let (pattern0, pattern1) = (self.pattern0.clone(), self.pattern1.clone());
+        if self.three_way {
+            let base = self.pattern_base.clone();
+            self.jobs
+                .request_three_way(base, pattern0, pattern1, self.diff_method);
+        } else {
+            self.jobs.request(pattern0, pattern1, self.diff_method);
+        }
+    }
+
+    /// True if a three-way diff is shown but there is no base to diff against.
+    fn needs_base(&self) -> bool {
+        self.three_way && self.pattern_base.is_none()
+    }
 
-            tx
+    /// Files shown in the table, left to right.
+    fn shown_files(&self) -> &'static [WhichFile] {
+        if self.three_way {
+            &[WhichFile::Base, WhichFile::File0, WhichFile::File1]
+        } else {
+            &[WhichFile::File0, WhichFile::File1]
+        }
+    }
+
+    /// Cells of the files shown in the table, left to right.
+    fn shown_cells<'a>(&self, results: &'a DiffResults) -> Vec<&'a [HexCell]> {
+        self.shown_files()
+            .iter()
+            .map(|side| side.cells(results))
+            .collect()
+    }
+
+    /// Where the shown files differ, computed with the minimap, and the
+    /// column they are in. Unaligned cells only line up within a file, so
+    /// then these are the differences of the selected column.
+    fn diff_runs(&mut self) -> (navigation::Runs, Option<usize>) {
+        let minimap = self.minimap();
+        if self.jobs.results().unaligned {
+            let column = self.selected_column.unwrap_or(0);
+            (minimap.column_runs(column), Some(column))
+        } else {
+            (minimap.runs(), None)
+        }
+    }
+
+    /// The minimap of the shown files and where they differ, computed once
+    /// per generation.
+    fn minimap(&mut self) -> Arc<Minimap> {
+        let results = self.jobs.results();
+        if let Some((generation, three_way, minimap)) = &self.minimap {
+            if *generation == results.generation && *three_way == self.three_way {
+                return minimap.clone();
+            }
+        }
+        let minimap = Arc::new(Minimap::new(&self.shown_cells(&results), Minimap::BUCKETS));
+        self.minimap = Some((results.generation, self.three_way, minimap.clone()));
+        minimap
+    }
+
+    /// The minimap of the whole files, framing the rows in view. Clicking or
+    /// dragging on it scrolls the table there.
+    fn show_minimap(&mut self, ui: &mut Ui) {
+        let minimap = self.minimap();
+        let rows = &self.visible_rows;
+        let visible = rows.start * HEX_GRID_WIDTH..rows.end * HEX_GRID_WIDTH;
+        if let Some(index) = minimap.show(ui, visible, self.selected_cell, SELECTED_CELL_COLOR) {
+            self.scroll_to_row = Some(index / HEX_GRID_WIDTH);
+        }
+    }
+
+    /// Selects the first cell of the difference after the selected cell, or
+    /// before it if not `forward`, and scrolls to it.
+    fn go_to_diff(&mut self, forward: bool) {
+        let (runs, column) = self.diff_runs();
+        self.go_to_run(&runs, forward, |_| column);
+    }
+
+    /// Selects the first cell of the run of `runs` after the selected cell,
+    /// or before it if not `forward`, and scrolls to it. `column(run)` is the
+    /// table column run `run` is in, if in one.
+    fn go_to_run(
+        &mut self,
+        runs: &[Range<usize>],
+        forward: bool,
+        column: impl Fn(usize) -> Option<usize>,
+    ) {
+        let run = if forward {
+            navigation::next_run(runs, self.selected_cell)
+        } else {
+            navigation::previous_run(runs, self.selected_cell)
         };
+        if let Some(run) = run {
+            self.go_to_cell(runs[run].start, column(run));
+        }
+    }
 
-        let cancel_job = self.cancel_job.clone();
-        let worker = move |_s: &rayon::Scope<'_>| {
-            let pattern0 = pattern0.lock().unwrap();
-            let pattern1 = pattern1.lock().unwrap();
+    /// Selects cell `index`, of table column `column` if it's in one, and
+    /// scrolls to it.
+    fn go_to_cell(&mut self, index: usize, column: Option<usize>) {
+        self.selected_cell = Some(index);
+        self.selected_column = column;
+        self.scroll_to_row = Some(index / HEX_GRID_WIDTH);
+    }
 
-            let request_repaint = || {
-                #[cfg(target_arch = "wasm32")]
-                refresh_egui_tx.unbounded_send(()).unwrap();
+    /// Goes to the offset typed in the go-to box, in the file chosen beside it.
+    fn go_to_offset(&mut self) {
+        let offset = match search::parse_offset(&self.go_to_text) {
+            Ok(offset) => offset,
+            Err(e) => return self.show_error(e),
+        };
+        let results = self.jobs.results();
+        let side = self.go_to_file;
+        let row_offsets = self.row_offsets(&results);
+        let column = FileColumn {
+            name: side.name(),
+            cells: side.cells(&results),
+            row_offsets: &row_offsets[side.index()],
+        };
+        match cell_info::cell_index(&column, HEX_GRID_WIDTH, offset) {
+            Some(index) => {
+                let shown = self.shown_files().iter().position(|&shown| shown == side);
+                self.go_to_cell(index, shown)
+            }
+            None => self.show_error(format!("{} has no byte at 0x{offset:X}", side.name())),
+        }
+    }
 
-                #[cfg(not(target_arch = "wasm32"))]
-                egui_context.request_repaint();
-            };
+    /// Where the last search was found in the shown files, or None until a
+    /// search job has found it in the shown generation.
+    fn search_hits(&mut self) -> Option<Arc<Hits>> {
+        let (query, regex) = self.search_query.as_ref()?;
+        let results = self.jobs.results();
+        let files = self.shown_files().iter().map(|side| side.index()).collect();
+        self.searches.hits(query, regex, &results, files)
+    }
 
-            let (new_diffs0, new_diffs1) =
-                if let (Some(pattern0), Some(pattern1)) = (&*pattern0, &*pattern1) {
-                    let len = std::cmp::max(pattern0.len(), pattern1.len());
-                    match diff_method {
-                        DiffMethod::ByIndex => diff::get_diffs(pattern0, pattern1, 0..len),
-                        DiffMethod::BpeGreedy00 => {
-                            let f = |x| {
-                                tx.send(x).unwrap();
-                                request_repaint();
-                            };
-                            println!("starting new_iterative");
-                            let mut bpe = Bpe::new_iterative(&[pattern0, pattern1]);
-                            println!("finished new_iterative");
-                            while bpe.init_in_progress.is_some() {
-                                bpe.init_step(Some(f));
-
-                                if cancel_job.load(Ordering::Acquire) {
-                                    return;
-                                }
-                            }
+    /// Collects the hits of the running search, and goes to the hit asked for
+    /// once they are found.
+    fn poll_searches(&mut self) {
+        self.searches.poll();
+        if let Some(forward) = self.pending_hit {
+            if self.search_hits().is_some() {
+                self.pending_hit = None;
+                self.go_to_hit(forward);
+            }
+        }
+    }
 
-                            let pattern0 = bpe.encode(pattern0);
-                            let pattern1 = bpe.encode(pattern1);
+    /// Searches for the text of the search box, and goes to the first hit
+    /// after the selected cell. Searching again goes to the next one.
+    fn search(&mut self) {
+        let query = Query {
+            text: self.search_text.clone(),
+            kind: self.search_kind,
+        };
+        if self.search_query.as_ref().map(|(searched, _)| searched) != Some(&query) {
+            match query.regex() {
+                Ok(regex) => self.search_query = Some((query, regex)),
+                Err(e) => {
+                    self.show_error(format!("can't search: {e}"));
+                    self.search_query = None;
+                    self.pending_hit = None;
+                    self.searches.clear();
+                    return;
+                }
+            }
+        }
+        self.go_to_hit(true);
+    }
 
-                            let matches = matcher::greedy00(&pattern0, &pattern1);
-                            test_utils::matches_to_cells(&matches, |x| bpe.decode(x.clone()))
-                        }
+    /// Goes to the next hit of the last search, or the previous one if not
+    /// `forward`. If the search is still running, goes there once it's done.
+    fn go_to_hit(&mut self, forward: bool) {
+        let Some(hits) = self.search_hits() else {
+            if self.search_query.is_some() {
+                self.pending_hit = Some(forward);
+            }
+            return;
+        };
+        match self.hit_column() {
+            Some(column) => {
+                let runs = hits.columns.get(column).map_or(&[][..], Vec::as_slice);
+                self.go_to_run(runs, forward, |_| Some(column))
+            }
+            None => self.go_to_run(&hits.all, forward, |run| Some(hits.all_columns[run])),
+        }
+    }
+
+    /// The column search hits are moved between in. Unaligned cells only line
+    /// up within a file, so then only the selected column's hits are; else
+    /// all hits are, in every column.
+    fn hit_column(&self) -> Option<usize> {
+        self.jobs
+            .results()
+            .unaligned
+            .then(|| self.selected_column.unwrap_or(0))
+    }
+
+    /// The go-to-offset and search boxes.
+    fn show_search(&mut self, ui: &mut Ui) {
+        let entered = |ui: &Ui, response: &egui::Response| {
+            response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter))
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("go to");
+            let response = ui.add(
+                TextEdit::singleline(&mut self.go_to_text)
+                    .hint_text("hex offset")
+                    .desired_width(100.0),
+            );
+            egui::ComboBox::from_id_salt("go_to_file")
+                .selected_text(self.go_to_file.name())
+                .show_ui(ui, |ui| {
+                    for &side in self.shown_files() {
+                        ui.selectable_value(&mut self.go_to_file, side, side.name());
                     }
-                } else {
-                    (vec![], vec![])
-                };
-            log::info!("started updating diffs");
+                });
+            if ui.button("go").clicked() || entered(ui, &response) {
+                self.go_to_offset();
+            }
+
+            ui.separator();
+
+            ui.label("find");
+            let hint = match self.search_kind {
+                SearchKind::Hex => "de ad be ef",
+                SearchKind::Text => "text",
+                SearchKind::Regex => r"PK\x03\x04",
+            };
+            let response = ui.add(
+                TextEdit::singleline(&mut self.search_text)
+                    .hint_text(hint)
+                    .desired_width(200.0),
+            );
+            for kind in [SearchKind::Hex, SearchKind::Text, SearchKind::Regex] {
+                let text = format!("{kind:?}").to_lowercase();
+                ui.selectable_value(&mut self.search_kind, kind, text);
+            }
+            if ui.button("find").clicked() || entered(ui, &response) {
+                self.search();
+                response.request_focus();
+            }
+
+            let Some(hits) = self.search_hits() else {
+                if self.searches.is_running() {
+                    ui.spinner();
+                    ui.label("searching");
+                }
+                return;
+            };
+            let runs = match self.hit_column() {
+                Some(column) => hits.columns.get(column).map_or(&[][..], Vec::as_slice),
+                None => &hits.all,
+            };
+            let previous = navigation::previous_run(runs, self.selected_cell);
+            if ui
+                .add_enabled(previous.is_some(), egui::Button::new("⏶"))
+                .on_hover_text(ui.ctx().format_shortcut(&PREVIOUS_HIT_SHORTCUT))
+                .clicked()
             {
-                let mut diffs0 = diffs0.lock().unwrap();
-                *diffs0 = new_diffs0;
+                self.go_to_hit(false);
             }
+            let next = navigation::next_run(runs, self.selected_cell);
+            if ui
+                .add_enabled(next.is_some(), egui::Button::new("⏷"))
+                .on_hover_text(ui.ctx().format_shortcut(&NEXT_HIT_SHORTCUT))
+                .clicked()
             {
-                let mut diffs1 = diffs1.lock().unwrap();
-                *diffs1 = new_diffs1;
+                self.go_to_hit(true);
             }
-            log::info!("finished updating diffs");
+            let current = self
+                .selected_cell
+                .and_then(|index| navigation::run_at(runs, index));
+            ui.label(match (current, runs.len()) {
+                (_, 0) => "not found".to_string(),
+                (Some(hit), n) => format!("hit {} of {n}", hit + 1),
+                (None, n) => format!("{n} hits"),
+            });
+        });
+    }
 
-            request_repaint();
-        };
+    /// Buttons moving between the differences. The minimap shows where they
+    /// are in the files.
+    fn show_navigation(&mut self, ui: &mut Ui) {
+        let (runs, _) = self.diff_runs();
+        let previous_shortcut = ui.ctx().format_shortcut(&PREVIOUS_DIFF_SHORTCUT);
+        let next_shortcut = ui.ctx().format_shortcut(&NEXT_DIFF_SHORTCUT);
+
+        ui.horizontal(|ui| {
+            let previous = navigation::previous_run(&runs, self.selected_cell);
+            if ui
+                .add_enabled(previous.is_some(), egui::Button::new("⏶ previous diff"))
+                .on_hover_text(previous_shortcut)
+                .clicked()
+            {
+                self.go_to_diff(false);
+            }
+            let next = navigation::next_run(&runs, self.selected_cell);
+            if ui
+                .add_enabled(next.is_some(), egui::Button::new("⏷ next diff"))
+                .on_hover_text(next_shortcut)
+                .clicked()
+            {
+                self.go_to_diff(true);
+            }
 
-        let job_running = self.job_running.clone();
-        let cancel_job = self.cancel_job.clone();
-        rayon::spawn(move || {
-            rayon::scope(|s| {
-                s.spawn(worker);
+            let current = self
+                .selected_cell
+                .and_then(|index| navigation::run_at(&runs, index));
+            ui.label(match (current, runs.len()) {
+                (_, 0) => "no differences".to_string(),
+                (Some(run), n) => format!("difference {} of {n}", run + 1),
+                (None, n) => format!("{n} differences"),
             });
-            job_running.store(false, Ordering::Release);
-            cancel_job.store(false, Ordering::Release);
         });
     }
 
-    fn add_header_row(&mut self, mut header: TableRow<'_, '_>) {
-        let no_pattern = "[none]".to_string();
+    fn show_table(&mut self, ui: &mut Ui) {
+        // Address, hex and ascii columns for each file.
+        let columns = 3 * self.shown_files().len();
+        let mut table = TableBuilder::new(ui)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .striped(true)
+            .columns(Column::auto().resizable(true), columns - 1)
+            .column(Column::remainder());
+        if let Some(row) = self.scroll_to_row.take() {
+            table = table.scroll_to_row(row, Some(egui::Align::Center));
+        }
+        table
+            .header(20.0, |header| self.add_header_row(header))
+            .body(|body| self.add_body_contents(body));
+    }
 
-        header.col(|ui| {
-            ui.heading("address");
-        });
-        header.col(|ui| {
-            ui.vertical(|ui| {
-                ui.heading(self.source_name0.as_ref().unwrap_or(&no_pattern));
-                ui.horizontal(|ui| {
-                    let text = drop_select_text(self.file_drop_target == WhichFile::File0);
-                    ui.selectable_value(&mut self.file_drop_target, WhichFile::File0, text)
-                        .highlight();
-                    if ui.button("randomize").clicked() {
-                        if self.try_set_pattern0(random_pattern()) {
-                            self.source_name0 = Some("random".to_string());
-                            self.update_diffs();
-                        }
-                    }
-                });
+    fn add_header_row(&mut self, mut header: TableRow<'_, '_>) {
+        for &side in self.shown_files() {
+            header.col(|ui| {
+                ui.heading("address");
             });
-        });
-        header.col(|_| {});
-        header.col(|ui| {
-            ui.vertical(|ui| {
-                ui.heading(self.source_name1.as_ref().unwrap_or(&no_pattern));
-                ui.horizontal(|ui| {
-                    let text = drop_select_text(self.file_drop_target == WhichFile::File1);
-                    ui.selectable_value(&mut self.file_drop_target, WhichFile::File1, text)
-                        .highlight();
-                    if ui.button("randomize").clicked() {
-                        if self.try_set_pattern1(random_pattern()) {
-                            self.source_name1 = Some("random".to_string());
-                            self.update_diffs();
-                        }
-                    }
-                });
+            header.col(|ui| self.add_file_header(ui, side));
+            header.col(|_| {});
+        }
+    }
+
+    /// Name, drop target and buttons of the file shown in `side`.
+    fn add_file_header(&mut self, ui: &mut Ui, side: WhichFile) {
+        let name = match side {
+            WhichFile::File0 => &self.source_name0,
+            WhichFile::File1 => &self.source_name1,
+            WhichFile::Base => &self.source_name_base,
+        };
+        let name = name.as_deref().unwrap_or("[none]");
+        let heading = match (self.three_way, side) {
+            (false, _) => name.to_string(),
+            (true, WhichFile::Base) => format!("base: {name}"),
+            (true, WhichFile::File0) => format!("A: {name}"),
+            (true, WhichFile::File1) => format!("B: {name}"),
+        };
+
+        ui.vertical(|ui| {
+            ui.heading(heading);
+            ui.horizontal(|ui| {
+                let text = drop_select_text(self.file_drop_target == side);
+                ui.selectable_value(&mut self.file_drop_target, side, text)
+                    .highlight();
+                if ui.button("randomize").clicked() {
+                    let pattern = Arc::new(Input::from(random_pattern()));
+                    self.set_file(side, "random".to_string(), pattern);
+                    self.update_diffs();
+                }
+                self.add_open_buttons(ui, side);
             });
         });
     }
 
-    fn add_body_contents(&self, body: TableBody<'_>) {
+    /// Row offsets of the cells of file 0, file 1 and the base in `results`,
+    /// computed once per generation.
+    fn row_offsets(&mut self, results: &DiffResults) -> Arc<[Vec<usize>; 3]> {
+        if let Some((generation, offsets)) = &self.row_offsets {
+            if *generation == results.generation {
+                return offsets.clone();
+            }
+        }
+        let offsets = Arc::new(
+            [&results.diffs0, &results.diffs1, &results.diffs_base]
+                .map(|cells| cell_info::row_offsets(cells, HEX_GRID_WIDTH)),
+        );
+        self.row_offsets = Some((results.generation, offsets.clone()));
+        offsets
+    }
+
+    fn add_body_contents(&mut self, body: TableBody<'_>) {
         fn color(c: usize) -> Color32 {
             let hi: u8 = 255;
             let lo: u8 = 128;
@@ -257,140 +975,378 @@ impl HexApp {
                 u8::wrapping_add(color.b(), 128),
             )
         }
+        /// Blue for `t` = 0.0 to red for `t` = 1.0.
+        fn gradient(t: f32) -> Color32 {
+            let t = t.clamp(0.0, 1.0);
+            let lo = 128.0;
+            Color32::from_rgb(
+                (lo + (255.0 - lo) * t) as u8,
+                lo as u8,
+                (255.0 - (255.0 - lo) * t) as u8,
+            )
+        }
 
-        let diffs0 = if let Ok(diffs0) = self.diffs0.try_lock() {
-            diffs0
-        } else {
-            return;
+        let results = self.jobs.results();
+        let shown_diffs = self.shown_cells(&results);
+
+        let vocabulary = &results.vocabulary;
+        let count = |source_id: usize| {
+            vocabulary
+                .get(source_id)
+                .map_or(0, |info| info.counts.iter().sum::<usize>())
+        };
+        let depth = |source_id: usize| vocabulary.get(source_id).map_or(0, |info| info.depth);
+        let max_depth = vocabulary.iter().map(|info| info.depth).max().unwrap_or(0);
+        let max_count = (0..vocabulary.len()).map(count).max().unwrap_or(0);
+        let color_mode = self.color_mode;
+        let cell_color = |source_id: usize, provenance: Provenance| match color_mode {
+            ColorMode::Token => color(source_id),
+            ColorMode::Boundary if provenance.is_token_start() => color(1),
+            ColorMode::Boundary => color(3),
+            ColorMode::Depth => gradient(depth(source_id) as f32 / max_depth.max(1) as f32),
+            // Rare tokens are red.
+            ColorMode::Frequency => gradient(
+                1.0 - (count(source_id) as f32).ln_1p() / (max_count.max(1) as f32).ln_1p(),
+            ),
         };
 
-        let diffs1 = if let Ok(diffs1) = self.diffs1.try_lock() {
-            diffs1
-        } else {
-            return;
+        let row_offsets = self.row_offsets(&results);
+
+        // Cells of the token selected in the inspector are drawn inverted, and
+        // the selected cell is highlighted in every file.
+        let search_hits = self.search_hits();
+        let is_hit = |column: usize, index: usize| {
+            search_hits
+                .as_ref()
+                .is_some_and(|hits| navigation::run_at(&hits.columns[column], index).is_some())
+        };
+        let selected_cell = self.selected_cell;
+        let selected_column = self.selected_column;
+        let unaligned = results.unaligned;
+        // Blank cells have no source token.
+        let selected = |column: usize, index: usize, source_id: Option<usize>, text: RichText| {
+            if selected_cell == Some(index)
+                && !(unaligned && selected_column.is_some_and(|selected| selected != column))
+            {
+                text.color(Color32::BLACK)
+                    .background_color(SELECTED_CELL_COLOR)
+            } else if is_hit(column, index) {
+                text.color(Color32::BLACK)
+                    .background_color(SEARCH_HIT_COLOR)
+            } else if source_id.is_some() && self.selected_token.map(TokenId::index) == source_id {
+                text.color(Color32::BLACK).background_color(Color32::WHITE)
+            } else {
+                text
+            }
         };
 
-        let hex_grid_width = 16;
+        let columns: Vec<FileColumn<'_>> = self
+            .shown_files()
+            .iter()
+            .zip(&shown_diffs)
+            .map(|(&side, diffs)| FileColumn {
+                name: side.name(),
+                cells: diffs,
+                row_offsets: &row_offsets[side.index()],
+            })
+            .collect();
+
+        // Hovering a cell shows where its byte is. Clicking it selects it, and
+        // shows the merge tree of its source token.
+        let clicked = Cell::new(None);
+        let click_label =
+            |ui: &mut Ui, column: usize, index: usize, source_id: usize, text: RichText| {
+                if ui
+                    .add(Label::new(text).sense(Sense::click()))
+                    .on_hover_ui(|ui| {
+                        cell_info::show(
+                            ui,
+                            &columns,
+                            HEX_GRID_WIDTH,
+                            column,
+                            index,
+                            unaligned,
+                            vocabulary,
+                        )
+                    })
+                    .clicked()
+                {
+                    clicked.set(Some((column, index, TokenId::from_index(source_id))));
+                }
+            };
 
         let row_height = 18.0;
-        let num_rows = 1 + std::cmp::max(diffs0.len(), diffs1.len()) / hex_grid_width;
+        let num_rows = 1 + shown_diffs
+            .iter()
+            .map(|diffs| diffs.len())
+            .max()
+            .unwrap_or(0)
+            / HEX_GRID_WIDTH;
 
+        let drawn_rows = Cell::new(None);
         body.rows(row_height, num_rows, |mut row| {
             let row_index = row.index();
+            drawn_rows.set(match drawn_rows.get() {
+                None => Some((row_index, row_index)),
+                Some((first, _)) => Some((first, row_index)),
+            });
 
-            let add_hex_row = |ui: &mut Ui, diffs: &Vec<HexCell>| {
-                (0..hex_grid_width).for_each(|i| {
-                    let cell = diffs.get(i + row_index * hex_grid_width);
-
-                    match cell {
-                        Some(&HexCell::Same { value, source_id }) => ui.label(
-                            RichText::new(format!("{value:02X}"))
-                                .color(color(source_id))
-                                .monospace(),
-                        ),
-                        Some(&HexCell::Diff { value, source_id }) => {
-                            let color = color(source_id);
-                            let contrast = contrast(color);
-                            ui.label(
-                                RichText::new(format!("{value:02X}"))
-                                    .color(contrast)
-                                    .background_color(color)
-                                    .monospace(),
-                            )
-                        }
-
-                        Some(&HexCell::Blank) => ui.monospace("__"),
-                        None => ui.monospace("xx"),
-                    };
-                });
-            };
+            // Shows a row of cells of `columns[column]`, each as `text(value)`.
+            let add_row =
+                |ui: &mut Ui, column: usize, text: fn(u8) -> String, blank: &str, missing: &str| {
+                    let diffs = columns[column].cells;
+                    (0..HEX_GRID_WIDTH).for_each(|i| {
+                        let index = i + row_index * HEX_GRID_WIDTH;
+                        let cell = diffs.get(index);
 
-            let add_ascii_row = |ui: &mut Ui, diffs: &Vec<HexCell>| {
-                (0..hex_grid_width).for_each(|i| {
-                    let cell = diffs.get(i + row_index * hex_grid_width);
-
-                    match cell {
-                        Some(&HexCell::Same { value, source_id }) => ui.label(
-                            RichText::new(format!("{}", value as char))
-                                .color(color(source_id))
-                                .monospace(),
-                        ),
-                        Some(&HexCell::Diff { value, source_id }) => {
-                            let color = color(source_id);
-                            let contrast = contrast(color);
-
-                            ui.label(
-                                RichText::new(format!("{}", value as char))
-                                    .color(contrast)
-                                    .background_color(color)
-                                    .monospace(),
-                            )
+                        match cell {
+                            Some(&HexCell::Same {
+                                value,
+                                source_id,
+                                provenance,
+                            }) => click_label(
+                                ui,
+                                column,
+                                index,
+                                source_id,
+                                selected(
+                                    column,
+                                    index,
+                                    Some(source_id),
+                                    RichText::new(text(value))
+                                        .color(cell_color(source_id, provenance))
+                                        .monospace(),
+                                ),
+                            ),
+                            Some(&HexCell::Diff {
+                                value,
+                                source_id,
+                                provenance,
+                            }) => {
+                                let color = cell_color(source_id, provenance);
+                                let contrast = contrast(color);
+                                click_label(
+                                    ui,
+                                    column,
+                                    index,
+                                    source_id,
+                                    selected(
+                                        column,
+                                        index,
+                                        Some(source_id),
+                                        RichText::new(text(value))
+                                            .color(contrast)
+                                            .background_color(color)
+                                            .monospace(),
+                                    ),
+                                )
+                            }
+                            Some(&HexCell::Conflict {
+                                value, source_id, ..
+                            }) => click_label(
+                                ui,
+                                column,
+                                index,
+                                source_id,
+                                selected(
+                                    column,
+                                    index,
+                                    Some(source_id),
+                                    RichText::new(text(value))
+                                        .color(Color32::WHITE)
+                                        .background_color(CONFLICT_COLOR)
+                                        .monospace(),
+                                ),
+                            ),
+                            Some(&HexCell::Blank) => {
+                                // Blank cells are aligned with a byte of another file.
+                                ui.label(selected(
+                                    column,
+                                    index,
+                                    None,
+                                    RichText::new(blank).monospace(),
+                                ));
+                            }
+                            None => {
+                                ui.monospace(missing);
+                            }
                         }
-                        Some(&HexCell::Blank) => ui.monospace("_"),
-                        None => ui.monospace("x"),
-                    };
-                });
+                    });
+                };
+
+            // The offset in its file of the first byte of the row.
+            let add_address = |ui: &mut Ui, column: &FileColumn<'_>| {
+                let start = row_index * HEX_GRID_WIDTH;
+                let has_bytes = column
+                    .cells
+                    .iter()
+                    .skip(start)
+                    .take(HEX_GRID_WIDTH)
+                    .any(|cell| *cell != HexCell::Blank);
+                if has_bytes {
+                    let offset = column.row_offsets[row_index];
+                    ui.label(RichText::new(format!("{offset:08X}")).monospace());
+                } else {
+                    ui.label(RichText::new("--------").monospace().weak());
+                }
             };
 
-            row.col(|ui| {
-                ui.label(RichText::new(format!("{:08X}", row_index * hex_grid_width)).monospace());
-            });
-            row.col(|ui| add_hex_row(ui, &diffs0));
-            row.col(|ui| add_ascii_row(ui, &diffs0));
-            row.col(|ui| add_hex_row(ui, &diffs1));
-            row.col(|ui| add_ascii_row(ui, &diffs1));
+            for (column, file) in columns.iter().enumerate() {
+                row.col(|ui| add_address(ui, file));
+                row.col(|ui| add_row(ui, column, |value| format!("{value:02X}"), "__", "xx"));
+                row.col(|ui| add_row(ui, column, |value| format!("{}", value as char), "_", "x"));
+            }
         });
+
+        if let Some((first, last)) = drawn_rows.get() {
+            self.visible_rows = first..last + 1;
+        }
+        if let Some((column, index, id)) = clicked.get() {
+            let deselect = self.selected_cell == Some(index)
+                && (!results.unaligned || self.selected_column == Some(column));
+            self.selected_cell = (!deselect).then_some(index);
+            self.selected_column = Some(column);
+            self.merge_tree_token = Some(id);
+        }
     }
 }
 
 impl eframe::App for HexApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, RecentFiles::STORAGE_KEY, &self.recent_files);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let mut new_id = None;
-        if let Some(rx) = &mut self.update_new_id_rx {
-            for x in rx.try_iter() {
-                new_id = Some(x);
+        self.jobs.poll();
+        self.similarity.poll();
+        self.poll_searches();
+
+        #[cfg(target_arch = "wasm32")]
+        while let Ok((side, name, pattern)) = self.picked_files.1.try_recv() {
+            self.set_file(side, name, Arc::new(Input::from(pattern)));
+            self.update_diffs();
+        }
+
+        // Token selections refer to the vocabulary of the results they were made in.
+        let generation = self.jobs.results().generation;
+        if generation != self.shown_generation {
+            self.shown_generation = generation;
+            if !std::mem::take(&mut self.preserve_selection) {
+                self.selected_token = None;
+                self.selected_cell = None;
+                self.selected_column = None;
+                self.merge_tree_token = None;
             }
         }
 
-        ctx.input(|i| {
-            if let Some(dropped_file) = i.raw.dropped_files.first() {
-                // This should only be Some when running as a native app.
-                if let Some(path) = &dropped_file.path {
-                    if let Some(pattern) = std::fs::read(path).ok() {
-                        match self.file_drop_target {
-                            WhichFile::File0 => {
-                                if self.try_set_pattern0(pattern) {
-                                    self.source_name0 = Some(path.to_string_lossy().to_string());
-                                }
-                            }
-                            WhichFile::File1 => {
-                                if self.try_set_pattern1(pattern) {
-                                    self.source_name1 = Some(path.to_string_lossy().to_string());
-                                }
-                            }
-                        }
-                    } else {
-                        log::error!("failed to read file: {:?}", path);
-                    }
-                }
-                // This should only be Some when running as a web app.
-                else if let Some(bytes) = &dropped_file.bytes {
-                    match self.file_drop_target {
-                        WhichFile::File0 => {
-                            if self.try_set_pattern0(bytes.to_vec()) {
-                                self.source_name0 = Some(dropped_file.name.clone());
-                            }
-                        }
-                        WhichFile::File1 => {
-                            if self.try_set_pattern1(bytes.to_vec()) {
-                                self.source_name1 = Some(dropped_file.name.clone());
+        #[cfg(not(target_arch = "wasm32"))]
+        self.reload_changed_files(ctx);
+
+        // Shift+F8 first, since F8 alone also matches it.
+        if ctx.input_mut(|i| i.consume_shortcut(&PREVIOUS_DIFF_SHORTCUT)) {
+            self.go_to_diff(false);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&NEXT_DIFF_SHORTCUT)) {
+            self.go_to_diff(true);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&PREVIOUS_HIT_SHORTCUT)) {
+            self.go_to_hit(false);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&NEXT_HIT_SHORTCUT)) {
+            self.go_to_hit(true);
+        }
+
+        let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
+        if !dropped_files.is_empty() {
+            let (loaded, rejected) = files::read_dropped_files(&dropped_files);
+            for message in rejected {
+                self.show_error(format!("rejected dropped file {message}"));
+            }
+            self.open_dropped(loaded);
+        }
+
+        if !self.messages.is_empty() {
+            egui::TopBottomPanel::top("messages").show(ctx, |ui| {
+                self.messages.retain(|message| {
+                    ui.horizontal(|ui| {
+                        let dismissed = ui.small_button("✖").clicked();
+                        match message {
+                            Message::Error(text) => {
+                                ui.colored_label(ui.visuals().error_fg_color, text)
                             }
-                        }
-                    }
+                            Message::Notice(text) => ui.label(text),
+                        };
+                        !dismissed
+                    })
+                    .inner
+                });
+            });
+        }
+
+        egui::TopBottomPanel::bottom("progress").show(ctx, |ui| {
+            self.jobs.progress().show(ui);
+        });
+
+        if self.show_token_inspector {
+            egui::SidePanel::right("token_inspector")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    ui.heading("tokens");
+                    let results = self.jobs.results();
+                    token_inspector::show(ui, &results.vocabulary, &mut self.selected_token);
+                });
+        }
+
+        egui::SidePanel::right("minimap")
+            .resizable(false)
+            .exact_width(MINIMAP_WIDTH)
+            .show(ctx, |ui| self.show_minimap(ui));
+
+        egui::Window::new("vocabulary growth")
+            .open(&mut self.show_growth_chart)
+            .default_size([400.0, 400.0])
+            .show(ctx, |ui| {
+                let merges = self.jobs.progress().merges();
+                let inputs = merges.first().map_or(0, |info| info.pattern_lengths.len());
+                growth_chart::show(ui, merges, &training_names(inputs));
+            });
+
+        let mut open_pair = None;
+        egui::Window::new("file set")
+            .open(&mut self.show_file_set)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let names: Vec<_> = self
+                    .file_set
+                    .iter()
+                    .map(|file| file.name.as_str())
+                    .collect();
+                if let Some(matrix) = self.similarity.matrix() {
+                    ui.label("% of bytes matched, with one vocabulary for all files. Click a pair to diff it.");
+                    open_pair = file_set::show_matrix(ui, &names, matrix);
+                } else if let Some((done, total)) = self.similarity.step() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("compared {done} of {total} pairs"));
+                    });
                 }
-                self.update_diffs();
+            });
+        if let Some((i, j)) = open_pair {
+            self.open_pair(i, j);
+        }
+
+        if let Some(id) = self.merge_tree_token {
+            let mut open = true;
+            egui::Window::new("merge tree")
+                .open(&mut open)
+                .vscroll(true)
+                .show(ctx, |ui| {
+                    merge_tree::show(ui, id, &self.jobs.results().vocabulary);
+                });
+            if !open {
+                self.merge_tree_token = None;
             }
-        });
+        }
 
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
@@ -413,29 +1369,873 @@ impl eframe::App for HexApp {
                 }
 
                 if ui
-                    .add_enabled(
-                        self.job_running.load(Ordering::Acquire),
-                        egui::Button::new("cancel"),
+                    .selectable_value(&mut self.diff_method, BpeInTermsOf, "BPE In Terms Of")
+                    .on_hover_text(
+                        "highlight the bytes of each file that aren't in the range of the other's tokens",
                     )
                     .clicked()
                 {
-                    self.cancel_job.store(true, Ordering::Release);
+                    self.update_diffs();
+                }
+
+                if ui
+                    .add_enabled(self.jobs.is_running(), egui::Button::new("cancel"))
+                    .clicked()
+                {
+                    self.jobs.cancel();
+                }
+                ui.label(match self.jobs.state() {
+                    JobState::Idle => "idle".to_string(),
+                    JobState::Running(id) => format!("job {id} running"),
+                    JobState::Cancelling { id, queued: false } => format!("cancelling job {id}"),
+                    JobState::Cancelling { id, queued: true } => {
+                        format!("cancelling job {id}, next job queued")
+                    }
+                });
+
+                if let Some(similarity) = self.jobs.results().similarity {
+                    ui.label(format!(
+                        "matched {:.1}%  jaccard {:.3}  ncd {:.3}",
+                        similarity.same_fraction * 100.0,
+                        similarity.jaccard,
+                        similarity.ncd
+                    ))
+                    .on_hover_text(
+                        "bytes in matched blocks, overlap of the token sets, \
+                         and normalized compression distance with the trained grammar",
+                    );
+                }
+
+                if !self.file_set.is_empty() {
+                    let names: Vec<_> = self
+                        .file_set
+                        .iter()
+                        .map(|file| file.name.as_str())
+                        .collect();
+                    let text = format!("file set: {} files", names.len());
+                    ui.toggle_value(&mut self.show_file_set, text)
+                        .on_hover_text(names.join("\n"));
                 }
 
-                //display the new id
-                ui.label(RichText::new(format!("new id: {new_id:?}")));
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui
+                    .button("Open set…")
+                    .on_hover_text("compare any number of files")
+                    .clicked()
+                {
+                    if let Some(paths) = rfd::FileDialog::new().pick_files() {
+                        let (loaded, rejected): (Vec<_>, Vec<_>) = paths
+                            .iter()
+                            .map(|path| LoadedFile::open(path).map_err(|e| (path, e)))
+                            .partition(Result::is_ok);
+                        for (path, e) in rejected.into_iter().filter_map(Result::err) {
+                            self.show_error(format!("failed to read {}: {e}", path.display()));
+                        }
+                        self.set_file_set(loaded.into_iter().filter_map(Result::ok).collect());
+                    }
+                }
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let mut watching = self.watcher.is_some();
+                    if ui
+                        .toggle_value(&mut watching, "watch files")
+                        .on_hover_text("reload files when they change on disk")
+                        .changed()
+                    {
+                        self.set_watching(watching, ctx);
+                    }
+                }
+
+                if ui
+                    .toggle_value(&mut self.three_way, "3-way")
+                    .on_hover_text("diff file 0 (A) and file 1 (B) against a common base")
+                    .changed()
+                {
+                    if !self.three_way && self.file_drop_target == WhichFile::Base {
+                        self.file_drop_target = WhichFile::File0;
+                    }
+                    if !self.three_way && self.go_to_file == WhichFile::Base {
+                        self.go_to_file = WhichFile::File0;
+                    }
+                    self.update_diffs();
+                }
+                if self.needs_base() {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "load a base file to diff 3 ways",
+                    );
+                }
+
+                egui::ComboBox::from_label("color by")
+                    .selected_text(format!("{:?}", self.color_mode).to_lowercase())
+                    .show_ui(ui, |ui| {
+                        for mode in [
+                            ColorMode::Token,
+                            ColorMode::Boundary,
+                            ColorMode::Depth,
+                            ColorMode::Frequency,
+                        ] {
+                            let text = format!("{mode:?}").to_lowercase();
+                            ui.selectable_value(&mut self.color_mode, mode, text);
+                        }
+                    });
+
+                ui.toggle_value(&mut self.show_token_inspector, "tokens");
+                ui.toggle_value(&mut self.show_growth_chart, "growth chart");
             });
 
-            TableBuilder::new(ui)
-                .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
-                .striped(true)
-                .column(Column::auto().resizable(true))
-                .column(Column::auto().resizable(true))
-                .column(Column::auto().resizable(true))
-                .column(Column::auto().resizable(true))
-                .column(Column::remainder())
-                .header(20.0, |header| self.add_header_row(header))
-                .body(|body| self.add_body_contents(body));
+            self.show_navigation(ui);
+            self.show_search(ui);
+            self.show_table(ui);
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::Task;
+    use crate::snapshots::DiffResults;
+    use arb_comp06::bpe::Bpe;
+    use egui::{epaint::TextShape, Pos2, RawInput, Rect, Shape, Vec2};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// A piece of text drawn by the UI.
+    #[derive(Debug)]
+    struct Text {
+        text: String,
+        color: Color32,
+        background: Color32,
+        rect: Rect,
+    }
+
+    fn collect_texts(shape: &Shape, texts: &mut Vec<Text>) {
+        match shape {
+            Shape::Vec(shapes) => shapes.iter().for_each(|s| collect_texts(s, texts)),
+            Shape::Text(TextShape { pos, galley, .. }) => {
+                let format = &galley.job.sections[0].format;
+                texts.push(Text {
+                    text: galley.text().to_string(),
+                    color: format.color,
+                    background: format.background,
+                    rect: galley.rect.translate(pos.to_vec2()),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// An app whose jobs run inline.
+    fn inline_app() -> HexApp {
+        HexApp::with_jobs(
+            DiffJobs::with_spawner(|| {}, |task| task()),
+            SimilarityJobs::with_spawner(|| {}, |task| task()),
+            SearchJobs::with_spawner(|| {}, |task| task()),
+        )
+    }
+
+    /// An app showing `diffs0` and `diffs1`.
+    fn app_showing(diffs0: Vec<HexCell>, diffs1: Vec<HexCell>) -> HexApp {
+        let app = inline_app();
+        publish_cells(&app, diffs0, diffs1, false);
+        app
+    }
+
+    /// Publishes `diffs0` and `diffs1` as the next results of `app`, with
+    /// the bytes of their cells as the files.
+    fn publish_cells(app: &HexApp, diffs0: Vec<HexCell>, diffs1: Vec<HexCell>, unaligned: bool) {
+        let bytes = |cells: &[HexCell]| {
+            let bytes: Vec<u8> = cells
+                .iter()
+                .filter_map(|cell| match *cell {
+                    HexCell::Same { value, .. }
+                    | HexCell::Diff { value, .. }
+                    | HexCell::Conflict { value, .. } => Some(value),
+                    HexCell::Blank => None,
+                })
+                .collect();
+            Some(Arc::new(Input::from(bytes)))
+        };
+        let generation = app.jobs.results().generation + 1;
+        assert!(app.jobs.publish(DiffResults {
+            generation,
+            files: [bytes(&diffs0), bytes(&diffs1), None],
+            diffs0,
+            diffs1,
+            unaligned,
+            vocabulary: vec![],
+            ..Default::default()
+        }));
+    }
+
+    /// Draws the hex table of `app` in a headless context, returning its text.
+    fn render(app: &mut HexApp) -> Vec<Text> {
+        let ctx = egui::Context::default();
+        let input = || RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(2000.0, 600.0))),
+            ..Default::default()
+        };
+
+        // The first frame only measures the auto-sized columns.
+        let mut run = || {
+            ctx.run(input(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| app.show_table(ui));
+            })
+        };
+        let _ = run();
+        let output = run();
+
+        let mut texts = vec![];
+        for clipped in &output.shapes {
+            collect_texts(&clipped.shape, &mut texts);
+        }
+        texts
+    }
+
+    fn find<'a>(texts: &'a [Text], text: &str) -> Vec<&'a Text> {
+        texts.iter().filter(|t| t.text == text).collect()
+    }
+
+    fn find_one<'a>(texts: &'a [Text], text: &str) -> &'a Text {
+        let found = find(texts, text);
+        assert_eq!(found.len(), 1, "{text:?} should be drawn once");
+        found[0]
+    }
+
+    #[test]
+    fn test_header_row() {
+        let mut app = app_showing(vec![], vec![]);
+        app.source_name0 = Some("left.bin".to_string());
+        app.source_name1 = None;
+        let texts = render(&mut app);
+
+        assert_eq!(find(&texts, "address").len(), 2, "one per file");
+        let left = find_one(&texts, "left.bin");
+        let right = find_one(&texts, "[none]");
+        assert!(left.rect.left() < right.rect.left());
+
+        assert_eq!(find(&texts, "randomize").len(), 2);
+        let selected = find_one(&texts, drop_select_text(true));
+        let unselected = find_one(&texts, drop_select_text(false));
+        assert!(
+            selected.rect.left() < unselected.rect.left(),
+            "file 0 is the drop target"
+        );
+    }
+
+    #[test]
+    fn test_address_column() {
+        let cells = vec![
+            HexCell::Same {
+                value: 0,
+                source_id: 0,
+                provenance: Provenance::BYTE,
+            };
+            40
+        ];
+        let texts = render(&mut app_showing(cells.clone(), cells));
+
+        let addresses: Vec<_> = texts
+            .iter()
+            .filter(|t| t.text.len() == 8 && t.text.chars().all(|c| c.is_ascii_hexdigit()))
+            .map(|t| t.text.as_str())
+            .collect();
+        // Both files, row by row
+        assert_eq!(
+            addresses,
+            ["00000000", "00000000", "00000010", "00000010", "00000020", "00000020"]
+        );
+
+        // 40 cells and 8 placeholders per side
+        assert_eq!(find(&texts, "00").len(), 80);
+        assert_eq!(find(&texts, "xx").len(), 16);
+    }
+
+    #[test]
+    fn test_addresses_skip_blanks() {
+        let same = HexCell::Same {
+            value: 0,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        };
+        let mut diffs1 = vec![HexCell::Blank; 20];
+        diffs1.extend([same; 20]);
+        let texts = render(&mut app_showing(vec![same; 40], diffs1));
+
+        let address = |text: &str| find(&texts, text);
+        // Row 0 of file 1 is all blanks.
+        assert_eq!(address("--------").len(), 1);
+        assert_eq!(address("00000000").len(), 2);
+        // Row 1 of file 1 starts with its first byte.
+        assert_eq!(address("00000010").len(), 1);
+        let row1 = address("00000010")[0];
+        let file1_row1 = address("00000000")
+            .into_iter()
+            .find(|t| t.rect.left() > row1.rect.left())
+            .expect("file 1 row 1 address");
+        assert!((file1_row1.rect.top() - row1.rect.top()).abs() < 1.0);
+        // Row 2: 16 cells of row 0 and 4 of row 1 were blanks.
+        assert_eq!(address("00000020").len(), 1);
+        assert_eq!(address("0000000C").len(), 1);
+    }
+
+    #[test]
+    fn test_cell_labels_and_colors() {
+        let diffs0 = vec![
+            HexCell::Same {
+                value: b'A',
+                source_id: 0,
+                provenance: Provenance::BYTE,
+            },
+            HexCell::Diff {
+                value: b'B',
+                source_id: 1,
+                provenance: Provenance::BYTE,
+            },
+            HexCell::Blank,
+        ];
+        let diffs1 = vec![
+            HexCell::Same {
+                value: b'A',
+                source_id: 0,
+                provenance: Provenance::BYTE,
+            },
+            HexCell::Diff {
+                value: b'C',
+                source_id: 2,
+                provenance: Provenance::BYTE,
+            },
+            HexCell::Blank,
+        ];
+        let texts = render(&mut app_showing(diffs0, diffs1));
+
+        for same in find(&texts, "41").into_iter().chain(find(&texts, "A")) {
+            assert_eq!(same.color, Color32::from_rgb(255, 128, 128));
+            assert_eq!(same.background, Color32::TRANSPARENT);
+        }
+        assert_eq!(find(&texts, "41").len(), 2);
+        assert_eq!(find(&texts, "A").len(), 2);
+
+        // Diff cells are drawn in the contrasting color on their source color.
+        let diff0 = find_one(&texts, "42");
+        assert_eq!(diff0.color, Color32::from_rgb(127, 127, 0));
+        assert_eq!(diff0.background, Color32::from_rgb(255, 255, 128));
+        let diff1 = find_one(&texts, "43");
+        assert_eq!(diff1.color, Color32::from_rgb(0, 127, 0));
+        assert_eq!(diff1.background, Color32::from_rgb(128, 255, 128));
+        assert_eq!(find_one(&texts, "B").color, diff0.color);
+
+        // hex 0, ascii 0, hex 1, ascii 1
+        let b = find_one(&texts, "B");
+        let c = find_one(&texts, "C");
+        assert!(diff0.rect.left() < b.rect.left());
+        assert!(b.rect.left() < diff1.rect.left());
+        assert!(diff1.rect.left() < c.rect.left());
+
+        assert_eq!(find(&texts, "__").len(), 2);
+        assert_eq!(find(&texts, "_").len(), 2);
+        assert_eq!(find(&texts, "xx").len(), 26);
+        assert_eq!(find(&texts, "x").len(), 26);
+    }
+
+    #[test]
+    fn test_color_modes() {
+        let token = |value, offset| HexCell::Same {
+            value,
+            source_id: 256,
+            provenance: Provenance::new(offset),
+        };
+        let mut app = app_showing(vec![token(0x10, 0), token(0x11, 1)], vec![]);
+        // Token 256 is 0x10 0x11, merged once.
+        let bpe = Bpe::new(&[&[0x10, 0x11, 0x10, 0x11]]);
+        assert!(app.jobs.publish(DiffResults {
+            generation: app.jobs.results().generation + 1,
+            diffs0: app.jobs.results().diffs0.clone(),
+            vocabulary: token_inspector::vocabulary(&bpe, [&[], &[], &[]]),
+            ..Default::default()
+        }));
+
+        app.color_mode = ColorMode::Boundary;
+        let texts = render(&mut app);
+        assert_eq!(
+            find_one(&texts, "10").color,
+            Color32::from_rgb(255, 255, 128)
+        );
+        assert_eq!(
+            find_one(&texts, "11").color,
+            Color32::from_rgb(128, 255, 255)
+        );
+
+        // The deepest token is red.
+        app.color_mode = ColorMode::Depth;
+        let texts = render(&mut app);
+        assert_eq!(
+            find_one(&texts, "10").color,
+            Color32::from_rgb(255, 128, 128)
+        );
+        assert_eq!(
+            find_one(&texts, "11").color,
+            Color32::from_rgb(255, 128, 128)
+        );
+    }
+
+    #[test]
+    fn test_selected_cell_highlighted() {
+        let same = |value| HexCell::Same {
+            value,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        };
+        let mut app = app_showing(
+            vec![same(0x10), same(0x11)],
+            vec![same(0x10), HexCell::Blank],
+        );
+        app.selected_cell = Some(1);
+        let texts = render(&mut app);
+
+        // The selected cell, and the blank aligned with it.
+        for text in [find(&texts, "11"), find(&texts, "__")].concat() {
+            assert_eq!(text.background, SELECTED_CELL_COLOR);
+        }
+        assert_eq!(find(&texts, "__").len(), 1);
+        for text in find(&texts, "10") {
+            assert_ne!(text.background, SELECTED_CELL_COLOR);
+        }
+
+        // Unaligned cells with the same index are unrelated, so only the
+        // cell of the column it was picked in is highlighted.
+        let generation = app.jobs.results().generation + 1;
+        assert!(app.jobs.publish(DiffResults {
+            generation,
+            diffs0: vec![same(0x10), same(0x11)],
+            diffs1: vec![same(0x12), same(0x13)],
+            unaligned: true,
+            ..Default::default()
+        }));
+        app.selected_column = Some(0);
+        let texts = render(&mut app);
+        assert!(find(&texts, "11")
+            .iter()
+            .all(|text| text.background == SELECTED_CELL_COLOR));
+        assert!(find(&texts, "13")
+            .iter()
+            .all(|text| text.background != SELECTED_CELL_COLOR));
+    }
+
+    #[test]
+    fn test_go_to_diff() {
+        let same = HexCell::Same {
+            value: 0,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        };
+        let mut cells0 = vec![same; 100];
+        cells0[40] = HexCell::Diff {
+            value: 1,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        };
+        cells0[41] = cells0[40];
+        cells0[90] = cells0[40];
+        let diff = cells0[40];
+        let mut app = app_showing(cells0, vec![same; 100]);
+
+        let mut go = |forward| {
+            app.go_to_diff(forward);
+            (app.selected_cell, app.scroll_to_row.take())
+        };
+        assert_eq!(go(true), (Some(40), Some(2)));
+        assert_eq!(go(true), (Some(90), Some(5)));
+        assert_eq!(go(true), (Some(90), None), "no difference after the last");
+        assert_eq!(go(false), (Some(40), Some(2)));
+        assert_eq!(go(false), (Some(40), None));
+
+        // Unaligned files are navigated one at a time, in the selected column.
+        let (mut cells0, mut cells1) = (vec![same; 10], vec![same; 10]);
+        cells0[3] = diff;
+        cells1[7] = diff;
+        let generation = app.jobs.results().generation + 1;
+        assert!(app.jobs.publish(DiffResults {
+            generation,
+            diffs0: cells0,
+            diffs1: cells1,
+            unaligned: true,
+            ..Default::default()
+        }));
+        app.selected_cell = None;
+        app.selected_column = Some(1);
+        app.go_to_diff(true);
+        assert_eq!((app.selected_cell, app.selected_column), (Some(7), Some(1)));
+        app.selected_column = Some(0);
+        app.go_to_diff(false);
+        assert_eq!((app.selected_cell, app.selected_column), (Some(3), Some(0)));
+    }
+
+    #[test]
+    fn test_minimap_scrolls_table() {
+        let same = HexCell::Same {
+            value: 0,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        };
+        // 100 rows
+        let mut app = app_showing(vec![same; 1600], vec![same; 1600]);
+        render(&mut app);
+        assert_eq!(app.visible_rows.start, 0);
+        assert!(app.visible_rows.end < 100, "{:?}", app.visible_rows);
+
+        // Click three quarters of the way down a 400 pixel minimap.
+        let ctx = egui::Context::default();
+        let pos = Pos2::new(10.0, 300.0);
+        let button = |pressed| egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+        let frames = [
+            vec![egui::Event::PointerMoved(pos)],
+            vec![button(true)],
+            vec![button(false)],
+        ];
+        for events in frames {
+            let input = RawInput {
+                screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(32.0, 400.0))),
+                events,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::NONE)
+                    .show(ctx, |ui| app.show_minimap(ui));
+            });
+        }
+        assert_eq!(app.scroll_to_row, Some(75));
+    }
+
+    #[test]
+    fn test_go_to_offset() {
+        let same = HexCell::Same {
+            value: 0,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        };
+        let mut diffs1 = vec![HexCell::Blank; 20];
+        diffs1.extend([same; 20]);
+        let mut app = app_showing(vec![same; 40], diffs1);
+
+        app.go_to_text = "0x4".to_string();
+        app.go_to_file = WhichFile::File1;
+        app.go_to_offset();
+        assert_eq!((app.selected_cell, app.scroll_to_row), (Some(24), Some(1)));
+
+        app.go_to_text = "20".to_string();
+        app.go_to_file = WhichFile::File0;
+        app.go_to_offset();
+        assert_eq!((app.selected_cell, app.scroll_to_row), (Some(32), Some(2)));
+
+        app.go_to_file = WhichFile::File1;
+        app.go_to_offset();
+        assert_eq!(app.selected_cell, Some(32), "file 1 has 20 bytes");
+        assert_eq!(
+            app.messages,
+            [Message::Error("file 1 has no byte at 0x20".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_search_hits() {
+        let cells = |values: &[u8]| -> Vec<HexCell> {
+            values
+                .iter()
+                .map(|&value| HexCell::Same {
+                    value,
+                    source_id: 0,
+                    provenance: Provenance::BYTE,
+                })
+                .collect()
+        };
+        let mut app = app_showing(cells(b"\x10AB\x11\x12AB"), cells(b"\x20\x21AB"));
+        app.search_text = "41 42".to_string();
+        app.search();
+        assert_eq!((app.selected_cell, app.selected_column), (Some(1), Some(0)));
+        app.search();
+        assert_eq!(
+            app.selected_cell,
+            Some(5),
+            "searching again goes to the next hit"
+        );
+
+        app.selected_cell = None;
+        let texts = render(&mut app);
+        let hits: Vec<_> = texts
+            .iter()
+            .filter(|t| t.background == SEARCH_HIT_COLOR)
+            .map(|t| t.text.as_str())
+            .collect();
+        // Row by row: hex and ascii of file 0, then of file 1
+        assert_eq!(
+            hits,
+            ["41", "42", "41", "42", "A", "B", "A", "B", "41", "42", "A", "B"]
+        );
+
+        // Unaligned hits are moved between within the selected column.
+        publish_cells(&app, cells(b"AB\x10AB"), cells(b"\x20\x21\x22AB"), true);
+        app.selected_column = Some(1);
+        app.go_to_hit(true);
+        assert_eq!((app.selected_cell, app.selected_column), (Some(3), Some(1)));
+        app.go_to_hit(true);
+        assert_eq!(app.selected_cell, Some(3), "file 1 has one hit");
+        app.selected_column = Some(0);
+        app.go_to_hit(false);
+        assert_eq!((app.selected_cell, app.selected_column), (Some(0), Some(0)));
+
+        app.search_kind = SearchKind::Regex;
+        app.search_text = "(".to_string();
+        app.search();
+        assert!(app.search_query.is_none());
+        assert_eq!(app.messages.len(), 1);
+    }
+
+    #[test]
+    fn test_search_runs_as_job() {
+        let queue: Rc<RefCell<Vec<Task>>> = Rc::default();
+        let queued = queue.clone();
+        let mut app = HexApp::with_jobs(
+            DiffJobs::with_spawner(|| {}, |task| task()),
+            SimilarityJobs::with_spawner(|| {}, |task| task()),
+            SearchJobs::with_spawner(|| {}, move |task| queued.borrow_mut().push(task)),
+        );
+        let same = |value| HexCell::Same {
+            value,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        };
+        publish_cells(&app, vec![same(0x10), same(0x41)], vec![same(0x41)], false);
+
+        app.search_text = "41".to_string();
+        app.search();
+        assert_eq!(app.selected_cell, None, "the search hasn't run yet");
+        assert!(app.search_hits().is_none());
+
+        queue.borrow_mut().pop().unwrap()();
+        app.poll_searches();
+        assert_eq!(app.selected_cell, Some(0), "goes to the hit once found");
+        assert_eq!(app.search_hits().unwrap().all, [0..1, 1..2]);
+
+        // New results are searched again.
+        publish_cells(&app, vec![same(0x41)], vec![], false);
+        assert!(app.search_hits().is_none());
+        queue.borrow_mut().pop().unwrap()();
+        app.poll_searches();
+        let hits = app.search_hits().unwrap();
+        assert_eq!((hits.all.len(), hits.columns[1].len()), (1, 0));
+    }
+
+    #[test]
+    fn test_selected_token_inverted() {
+        let cells = vec![
+            HexCell::Same {
+                value: 0x10,
+                source_id: 0,
+                provenance: Provenance::BYTE,
+            },
+            HexCell::Same {
+                value: 0x11,
+                source_id: 1,
+                provenance: Provenance::BYTE,
+            },
+        ];
+        let mut app = app_showing(cells.clone(), cells);
+        app.selected_token = Some(TokenId(1));
+        let texts = render(&mut app);
+        assert_eq!(find(&texts, "11").len(), 2);
+        assert_eq!(find(&texts, "10").len(), 2);
+
+        for selected in find(&texts, "11") {
+            assert_eq!(selected.color, Color32::BLACK);
+            assert_eq!(selected.background, Color32::WHITE);
+        }
+        for unselected in find(&texts, "10") {
+            assert_eq!(unselected.color, Color32::from_rgb(255, 128, 128));
+            assert_eq!(unselected.background, Color32::TRANSPARENT);
+        }
+    }
+
+    fn loaded(name: &str) -> LoadedFile {
+        LoadedFile {
+            name: name.to_string(),
+            path: None,
+            data: Arc::new(Input::from(name.as_bytes().to_vec())),
+        }
+    }
+
+    #[test]
+    fn test_large_file_notice() {
+        let mut app = inline_app();
+        let pattern = |len| Arc::new(Input::from(vec![0; len]));
+        app.set_file(WhichFile::File0, "small".to_string(), pattern(100));
+        assert!(app.messages.is_empty());
+        app.set_file(
+            WhichFile::File1,
+            "big".to_string(),
+            pattern(LARGE_FILE_SIZE + 1),
+        );
+        assert!(
+            matches!(&app.messages[..], [Message::Notice(notice)] if notice.starts_with("big is 64 MiB")),
+            "{:?}",
+            app.messages
+        );
+    }
+
+    #[test]
+    fn test_reload() {
+        let dir = std::env::temp_dir().join(format!("egui_hex07_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.bin");
+        std::fs::write(&path, b"old").unwrap();
+
+        let mut app = inline_app();
+        app.open_path(WhichFile::File1, &path);
+        app.set_file(WhichFile::File0, "other".to_string(), loaded("x").data);
+        let recent = app.recent_files.paths().to_vec();
+        let name = app.source_name1.clone();
+
+        std::fs::write(&path, b"new!").unwrap();
+        let source_path = app.source_path1.clone().unwrap();
+        app.reload(WhichFile::File1, &source_path);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(app.pattern1.as_deref().map(|p| &p[..]), Some(&b"new!"[..]));
+        assert_eq!(app.source_name1, name);
+        assert_eq!(app.source_path1, Some(source_path));
+        assert_eq!(app.recent_files.paths(), recent);
+        assert_eq!(
+            app.source_name0.as_deref(),
+            Some("other"),
+            "only file 1 reloads"
+        );
+    }
+
+    #[test]
+    fn test_open_dropped() {
+        let mut app = inline_app();
+
+        app.file_drop_target = WhichFile::File1;
+        app.open_dropped(vec![loaded("one")]);
+        assert_eq!(app.source_name0.as_deref(), Some("zeroes0"));
+        assert_eq!(app.source_name1.as_deref(), Some("one"));
+
+        app.open_dropped(vec![loaded("a"), loaded("bc")]);
+        assert_eq!(app.source_name0.as_deref(), Some("a"));
+        assert_eq!(app.source_name1.as_deref(), Some("bc"));
+        assert_eq!(
+            app.pattern1.as_deref().map(|pattern| &pattern[..]),
+            Some(&b"bc"[..])
+        );
+        assert!(app.file_set.is_empty());
+        assert!(app.messages.is_empty());
+        assert_eq!(app.jobs.results().diffs1.len(), 2, "the diff was rerun");
+
+        app.open_dropped(vec![loaded("x"), loaded("y"), loaded("z")]);
+        assert_eq!(app.source_name0.as_deref(), Some("x"));
+        assert_eq!(app.source_name1.as_deref(), Some("y"));
+        let names: Vec<_> = app.file_set.iter().map(|file| file.name.as_str()).collect();
+        assert_eq!(names, ["x", "y", "z"]);
+        assert_eq!(
+            app.messages,
+            [Message::Notice("loaded 3 files as a file set".to_string())]
+        );
+        assert!(app.show_file_set);
+        app.similarity.poll();
+        assert_eq!(app.similarity.matrix().map(Vec::len), Some(3));
+
+        app.open_pair(2, 0);
+        assert_eq!(app.source_name0.as_deref(), Some("z"));
+        assert_eq!(app.source_name1.as_deref(), Some("x"));
+        assert_eq!(app.file_set.len(), 3, "the file set is kept");
+
+        app.open_dropped(vec![loaded("one")]);
+        assert!(app.file_set.is_empty(), "an ordinary drop replaces the set");
+        assert!(!app.show_file_set);
+        assert_eq!(app.similarity.matrix(), None);
+    }
+
+    #[test]
+    fn test_three_way_columns() {
+        let cell = |value| HexCell::Same {
+            value,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        };
+        let mut app = inline_app();
+        app.three_way = true;
+        app.source_name_base = Some("base.bin".to_string());
+        app.source_name0 = Some("a.bin".to_string());
+        app.source_name1 = Some("b.bin".to_string());
+        assert!(app.jobs.publish(DiffResults {
+            generation: 1,
+            diffs_base: vec![cell(0x10)],
+            diffs0: vec![cell(0x11)],
+            diffs1: vec![HexCell::Conflict {
+                value: 0x12,
+                source_id: 0,
+                provenance: Provenance::BYTE,
+            }],
+            vocabulary: vec![],
+            ..Default::default()
+        }));
+        let texts = render(&mut app);
+
+        // base, A and B, left to right
+        let base = find_one(&texts, "base: base.bin");
+        let a = find_one(&texts, "A: a.bin");
+        let b = find_one(&texts, "B: b.bin");
+        assert!(base.rect.left() < a.rect.left());
+        assert!(a.rect.left() < b.rect.left());
+        assert_eq!(find(&texts, "randomize").len(), 3);
+
+        let cell_base = find_one(&texts, "10");
+        let cell_a = find_one(&texts, "11");
+        let cell_b = find_one(&texts, "12");
+        assert!(cell_base.rect.left() < cell_a.rect.left());
+        assert!(cell_a.rect.left() < cell_b.rect.left());
+        assert_eq!(cell_b.background, CONFLICT_COLOR);
+        assert_eq!(cell_b.color, Color32::WHITE);
+    }
+
+    #[test]
+    fn test_three_way_requests() {
+        let mut app = inline_app();
+        app.three_way = true;
+        assert!(app.needs_base());
+        app.open_dropped(vec![loaded("o"), loaded("a"), loaded("b")]);
+        assert!(!app.needs_base());
+        assert_eq!(app.source_name_base.as_deref(), Some("o"));
+        assert_eq!(app.source_name0.as_deref(), Some("a"));
+        assert_eq!(app.source_name1.as_deref(), Some("b"));
+        let results = app.jobs.results();
+        assert_eq!(
+            results.diffs_base,
+            crate::diff::get_diffs3(b"o", b"a", b"b", 0..1).0
+        );
+
+        app.three_way = false;
+        app.update_diffs();
+        assert!(
+            app.jobs.results().diffs_base.is_empty(),
+            "the base is ignored"
+        );
+
+        // Training lengths are plotted for every input, the base first.
+        app.three_way = true;
+        app.diff_method = DiffMethod::BpeGreedy00;
+        app.open_dropped(vec![loaded("abab"), loaded("abab"), loaded("ab")]);
+        let merges = app.jobs.progress().merges();
+        assert_eq!(merges[0].pattern_lengths, [2, 2, 1]);
+        assert_eq!(training_names(3), ["base", "file 0", "file 1"]);
+        assert_eq!(training_names(2), ["file 0", "file 1"]);
+    }
+}
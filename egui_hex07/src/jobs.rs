@@ -0,0 +1,221 @@
+use arb_comp06::interrupt::CancelToken;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    mpsc, Arc,
+};
+
+pub type JobId = u64;
+
+/// A job body, run once on a worker thread.
+pub type Task = Box<dyn FnOnce() + Send>;
+
+/// Computes the result of one `Request` on a worker thread, sending
+/// `Message`s to the UI as it goes.
+pub type Work<Request, Message> = dyn Fn(Request, &Job, &dyn Fn(Message)) + Send + Sync;
+
+/// What the job manager is currently doing, for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Idle,
+    Running(JobId),
+    /// The running job was cancelled and hasn't stopped yet.
+    /// `queued` is true if a newer request will start once it does.
+    Cancelling {
+        id: JobId,
+        queued: bool,
+    },
+}
+
+/// Worker-side handle to one job.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: JobId,
+    cancel: CancelToken,
+    finished: Arc<AtomicBool>,
+    latest_id: Arc<AtomicU64>,
+}
+
+impl Job {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn cancel_token(&self) -> &CancelToken {
+        &self.cancel
+    }
+
+    /// True if this job hasn't been cancelled or superseded by a newer request.
+    /// Results of a job that isn't current must not be published.
+    pub fn is_current(&self) -> bool {
+        !self.is_cancelled() && self.latest_id.load(Ordering::Acquire) == self.id
+    }
+
+    /// Marks the job as stopped, allowing a queued request to start.
+    pub fn finish(&self) {
+        self.finished.store(true, Ordering::Release);
+    }
+}
+
+/// Runs at most one job at a time.
+///
+/// A new request cancels the running job and waits, superseding any older
+/// waiting request, until the running job stops.
+pub struct JobManager {
+    running: Option<Job>,
+    queued: bool,
+    latest_id: Arc<AtomicU64>,
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self {
+            running: None,
+            queued: false,
+            latest_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Requests a new job, cancelling the running one. Call `poll` to start it.
+    pub fn request(&mut self) {
+        self.latest_id.fetch_add(1, Ordering::AcqRel);
+        if let Some(job) = &self.running {
+            job.cancel.cancel();
+        }
+        self.queued = true;
+    }
+
+    /// Cancels the running job and drops any queued request.
+    pub fn cancel(&mut self) {
+        if let Some(job) = &self.running {
+            job.cancel.cancel();
+        }
+        self.queued = false;
+    }
+
+    /// Clears a finished job, then returns the queued job if it can start now.
+    pub fn poll(&mut self) -> Option<Job> {
+        if self
+            .running
+            .as_ref()
+            .is_some_and(|job| job.finished.load(Ordering::Acquire))
+        {
+            self.running = None;
+        }
+
+        if self.running.is_some() || !self.queued {
+            return None;
+        }
+        self.queued = false;
+
+        let job = Job {
+            id: self.latest_id.load(Ordering::Acquire),
+            cancel: CancelToken::new(),
+            finished: Arc::new(AtomicBool::new(false)),
+            latest_id: self.latest_id.clone(),
+        };
+        self.running = Some(job.clone());
+        Some(job)
+    }
+
+    pub fn state(&self) -> JobState {
+        match &self.running {
+            None => JobState::Idle,
+            Some(job) if job.is_cancelled() => JobState::Cancelling {
+                id: job.id,
+                queued: self.queued,
+            },
+            Some(job) => JobState::Running(job.id),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+}
+
+/// Runs `work` on worker threads for the UI, one request at a time.
+///
+/// Call `poll` once per frame: it starts a queued request when the previous
+/// job has stopped, and returns the messages sent by the running job.
+pub struct JobRunner<Request, Message> {
+    jobs: JobManager,
+    /// The latest request, until its job starts.
+    queued: Option<Request>,
+    rx: Option<mpsc::Receiver<Message>>,
+    work: Arc<Work<Request, Message>>,
+    request_repaint: Arc<dyn Fn() + Send + Sync>,
+    spawn: Box<dyn Fn(Task)>,
+}
+
+impl<Request: Send + 'static, Message: Send + 'static> JobRunner<Request, Message> {
+    /// Runs jobs with `spawn`. Workers call `request_repaint` after every
+    /// message and when they stop.
+    pub fn new(
+        work: impl Fn(Request, &Job, &dyn Fn(Message)) + Send + Sync + 'static,
+        request_repaint: impl Fn() + Send + Sync + 'static,
+        spawn: impl Fn(Task) + 'static,
+    ) -> Self {
+        Self {
+            jobs: JobManager::new(),
+            queued: None,
+            rx: None,
+            work: Arc::new(work),
+            request_repaint: Arc::new(request_repaint),
+            spawn: Box::new(spawn),
+        }
+    }
+
+    /// Queues `request`, cancelling the running job. Call `poll` to start it.
+    pub fn request(&mut self, request: Request) {
+        self.jobs.request();
+        self.queued = Some(request);
+    }
+
+    /// Cancels the running job and drops any queued request.
+    pub fn cancel(&mut self) {
+        self.jobs.cancel();
+        self.queued = None;
+    }
+
+    /// Starts the queued request if no job is running, returning its job id.
+    pub fn start_queued(&mut self) -> Option<JobId> {
+        let job = self.jobs.poll()?;
+        let request = self
+            .queued
+            .take()
+            .expect("a queued job has a queued request");
+        let id = job.id;
+        let work = self.work.clone();
+        let request_repaint = self.request_repaint.clone();
+
+        let (tx, rx) = mpsc::channel();
+        self.rx = Some(rx);
+
+        (self.spawn)(Box::new(move || {
+            // The receiver is dropped when a newer job starts.
+            let send = |message| {
+                tx.send(message).ok();
+                request_repaint();
+            };
+            work(request, &job, &send);
+            job.finish();
+
+            // Wake the UI so it can start a queued job.
+            request_repaint();
+        }));
+        Some(id)
+    }
+
+    /// Messages sent by the latest job since the last call.
+    pub fn messages(&self) -> impl Iterator<Item = Message> + '_ {
+        self.rx.iter().flat_map(|rx| rx.try_iter())
+    }
+
+    pub fn state(&self) -> JobState {
+        self.jobs.state()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.jobs.is_running()
+    }
+}
@@ -1,6 +1,21 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+mod cell_info;
+mod diff_jobs;
+mod file_set;
+mod files;
+mod growth_chart;
 mod hex_app;
+mod jobs;
+mod merge_tree;
+mod minimap;
+mod navigation;
+mod progress;
+mod search;
+mod snapshots;
+mod token_inspector;
+#[cfg(not(target_arch = "wasm32"))]
+mod watcher;
 pub use arb_comp06::diff;
 pub use hex_app::HexApp;
 
@@ -10,6 +10,9 @@ fn main() -> eframe::Result {
         .filter_level(log::LevelFilter::Info)
         .init();
 
+    // egui_hex07 [file0] [file1]
+    let paths: Vec<std::path::PathBuf> = std::env::args_os().skip(1).map(Into::into).collect();
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([400.0, 300.0])
@@ -24,7 +27,7 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "eframe template",
         native_options,
-        Box::new(|cc| Ok(Box::new(egui_hex07::HexApp::new(cc)))),
+        Box::new(move |cc| Ok(Box::new(egui_hex07::HexApp::with_files(cc, &paths)))),
     )
 }
 
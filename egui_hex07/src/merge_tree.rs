@@ -0,0 +1,46 @@
+use crate::token_inspector::{ascii_text, hex_text, TokenInfo};
+use arb_comp06::token::{Token, TokenId};
+use egui::{CollapsingHeader, RichText, Ui};
+
+fn node_text(info: &TokenInfo) -> String {
+    format!(
+        "{}: {} \"{}\"",
+        info.id.0,
+        hex_text(&info.bytes),
+        ascii_text(&info.bytes)
+    )
+}
+
+fn show_node(ui: &mut Ui, info: &TokenInfo, vocabulary: &[TokenInfo], default_open: bool) {
+    match info.token {
+        Token::Byte(_) => {
+            ui.monospace(node_text(info));
+        }
+        Token::Merge(id0, id1) => {
+            CollapsingHeader::new(RichText::new(node_text(info)).monospace())
+                .default_open(default_open)
+                .show(ui, |ui| {
+                    // Children are pushed under distinct ids so identical subtrees
+                    // (e.g. `Merge(x, x)`) keep separate collapsed states.
+                    for (i, id) in [id0, id1].into_iter().enumerate() {
+                        ui.push_id(i, |ui| match vocabulary.get(id.index()) {
+                            Some(child) => show_node(ui, child, vocabulary, false),
+                            None => {
+                                ui.label(format!("{}: not in vocabulary", id.0));
+                            }
+                        });
+                    }
+                });
+        }
+    }
+}
+
+/// Shows the merge tree of token `id` as nested collapsible nodes.
+pub fn show(ui: &mut Ui, id: TokenId, vocabulary: &[TokenInfo]) {
+    match vocabulary.get(id.index()) {
+        Some(info) => show_node(ui, info, vocabulary, true),
+        None => {
+            ui.label("no vocabulary (run a BPE diff method)");
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! A strip showing the whole of the aligned files at once, to find their
+//! differences and jump to them.
+
+use crate::diff::HexCell;
+use crate::navigation::Runs;
+use egui::{Color32, Rect, Sense, Stroke, StrokeKind, Ui, Vec2};
+use std::ops::Range;
+
+/// How many cells of a stretch of the aligned files are the same in every
+/// file, differ, or are blank padding in some file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Density {
+    pub same: usize,
+    pub diff: usize,
+    pub blank: usize,
+}
+
+const SAME_COLOR: Color32 = Color32::from_gray(70);
+const DIFF_COLOR: Color32 = Color32::from_rgb(230, 60, 60);
+const BLANK_COLOR: Color32 = Color32::from_rgb(70, 120, 230);
+
+impl Density {
+    fn add(&mut self, other: Density) {
+        self.same += other.same;
+        self.diff += other.diff;
+        self.blank += other.blank;
+    }
+
+    fn total(self) -> usize {
+        self.same + self.diff + self.blank
+    }
+
+    /// Gray if all cells are the same, else red for differing or blue for
+    /// blank cells, whichever there are more of. However few there are, they
+    /// are clearly colored, since finding them is the point.
+    fn color(self) -> Option<Color32> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let (count, color) = if self.diff >= self.blank {
+            (self.diff, DIFF_COLOR)
+        } else {
+            (self.blank, BLANK_COLOR)
+        };
+        if count == 0 {
+            return Some(SAME_COLOR);
+        }
+        let strength = (count as f32 / total as f32).max(0.5);
+        Some(SAME_COLOR.lerp_to_gamma(color, strength))
+    }
+}
+
+/// The densities of equal stretches of aligned files, and where they differ.
+#[derive(Debug, Default, PartialEq)]
+pub struct Minimap {
+    /// Cells in the longest file.
+    len: usize,
+    buckets: Vec<Density>,
+    /// Ranges of the cells that aren't `HexCell::Same` in every file.
+    runs: Runs,
+    /// Ranges of the differing or conflicting cells of each file, for files
+    /// whose cells don't line up with the others'.
+    column_runs: Vec<Runs>,
+}
+
+/// Adds cell `index` to the last of `runs`, or starts a new one after it.
+fn extend(runs: &mut Vec<Range<usize>>, index: usize) {
+    match runs.last_mut() {
+        Some(run) if run.end == index => run.end += 1,
+        _ => runs.push(index..index + 1),
+    }
+}
+
+impl Minimap {
+    /// Stretches a minimap is computed in, whatever height it is drawn at.
+    pub const BUCKETS: usize = 1024;
+
+    /// Densities of the aligned `columns` in at most `buckets` stretches. A
+    /// cell is blank if it is blank in any file, and differs if it isn't
+    /// `HexCell::Same` in every file. Both are part of a difference, as are
+    /// cells past the end of a shorter file. The differences of each file on
+    /// its own are found in the same pass.
+    pub fn new(columns: &[&[HexCell]], buckets: usize) -> Self {
+        let len = columns.iter().map(|cells| cells.len()).max().unwrap_or(0);
+        let mut densities = vec![Density::default(); buckets.min(len)];
+        let mut runs: Vec<Range<usize>> = vec![];
+        let mut column_runs: Vec<Vec<Range<usize>>> = vec![vec![]; columns.len()];
+        for index in 0..len {
+            let mut cells = columns.iter().map(|cells| cells.get(index));
+            for (cell, runs) in cells.clone().zip(&mut column_runs) {
+                if matches!(cell, Some(HexCell::Diff { .. } | HexCell::Conflict { .. })) {
+                    extend(runs, index);
+                }
+            }
+            // In u64, since usize is 32 bits on the web.
+            let bucket = index as u64 * densities.len() as u64 / len as u64;
+            let density = &mut densities[bucket as usize];
+            if cells.clone().any(|cell| cell == Some(&HexCell::Blank)) {
+                density.blank += 1;
+            } else if cells.all(|cell| matches!(cell, Some(HexCell::Same { .. }))) {
+                density.same += 1;
+                continue;
+            } else {
+                density.diff += 1;
+            }
+            extend(&mut runs, index);
+        }
+        Minimap {
+            len,
+            buckets: densities,
+            runs: runs.into(),
+            column_runs: column_runs.into_iter().map(Runs::from).collect(),
+        }
+    }
+
+    /// Where the files differ, for `navigation`.
+    pub fn runs(&self) -> Runs {
+        self.runs.clone()
+    }
+
+    /// Where file `column` has differing or conflicting cells, for
+    /// `navigation` within a file.
+    pub fn column_runs(&self, column: usize) -> Runs {
+        self.column_runs.get(column).cloned().unwrap_or_default()
+    }
+
+    /// Total density of the buckets drawn in the `part`th of `parts` equal
+    /// parts of the minimap.
+    fn density(&self, part: usize, parts: usize) -> Density {
+        let n = self.buckets.len();
+        let start = part * n / parts;
+        let end = ((part + 1) * n / parts).max(start + 1).min(n);
+        let mut density = Density::default();
+        for bucket in &self.buckets[start..end] {
+            density.add(*bucket);
+        }
+        density
+    }
+
+    /// Cell at `fraction` of the way down the minimap.
+    fn cell_at(&self, fraction: f32) -> usize {
+        ((fraction.clamp(0.0, 1.0) * self.len as f32) as usize).min(self.len.saturating_sub(1))
+    }
+
+    /// Draws the minimap, filling the available height, with the `visible`
+    /// cells framed and cell `current` marked in `current_color`. Returns the
+    /// cell clicked or dragged to.
+    pub fn show(
+        &self,
+        ui: &mut Ui,
+        visible: Range<usize>,
+        current: Option<usize>,
+        current_color: Color32,
+    ) -> Option<usize> {
+        let size = Vec2::new(ui.available_width(), ui.available_height());
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click_and_drag());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+        if self.len == 0 {
+            return None;
+        }
+
+        let rows = rect.height().max(1.0) as usize;
+        for row in 0..rows {
+            if let Some(color) = self.density(row, rows).color() {
+                let top = rect.top() + row as f32;
+                let line = Rect::from_x_y_ranges(rect.x_range(), top..=top + 1.0);
+                painter.rect_filled(line, 0.0, color);
+            }
+        }
+
+        let y = |index: usize| rect.top() + rect.height() * index as f32 / self.len as f32;
+        if !visible.is_empty() {
+            let view = Rect::from_x_y_ranges(rect.x_range(), y(visible.start)..=y(visible.end));
+            let stroke = Stroke::new(1.0, ui.visuals().strong_text_color());
+            painter.rect_stroke(view, 0.0, stroke, StrokeKind::Inside);
+        }
+        if let Some(index) = current {
+            painter.hline(rect.x_range(), y(index), (2.0, current_color));
+        }
+
+        let pointer = response.interact_pointer_pos()?;
+        (response.clicked() || response.dragged())
+            .then(|| self.cell_at((pointer.y - rect.top()) / rect.height()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::Provenance;
+
+    fn same() -> HexCell {
+        HexCell::Same {
+            value: 0,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        }
+    }
+
+    fn diff() -> HexCell {
+        HexCell::Diff {
+            value: 0,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        }
+    }
+
+    #[test]
+    fn test_densities() {
+        let cells0 = [
+            same(),
+            same(),
+            diff(),
+            same(),
+            diff(),
+            diff(),
+            same(),
+            same(),
+        ];
+        let cells1 = [same(), same(), same(), same(), HexCell::Blank, diff()];
+        let minimap = Minimap::new(&[&cells0, &cells1], 4);
+        let density = |same, diff, blank| Density { same, diff, blank };
+        assert_eq!(
+            minimap.buckets,
+            [
+                density(2, 0, 0),
+                density(1, 1, 0),
+                density(0, 1, 1),
+                // Past the end of file 1
+                density(0, 2, 0),
+            ]
+        );
+
+        // Drawn in fewer rows than buckets, rows add up buckets.
+        assert_eq!(minimap.density(0, 2), density(3, 1, 0));
+        assert_eq!(minimap.density(1, 2), density(0, 3, 1));
+        // and in more rows, buckets are repeated.
+        assert_eq!(minimap.density(1, 8), density(2, 0, 0));
+        assert_eq!(minimap.density(7, 8), density(0, 2, 0));
+
+        assert_eq!(minimap.cell_at(0.0), 0);
+        assert_eq!(minimap.cell_at(0.5), 4);
+        assert_eq!(minimap.cell_at(1.0), 7);
+
+        assert_eq!(*minimap.runs(), [2..3, 4..8]);
+        assert_eq!(*minimap.column_runs(0), [2..3, 4..6]);
+        let runs1 = minimap.column_runs(1);
+        assert_eq!((runs1.len(), runs1.first()), (1, Some(&(5..6))));
+        assert!(minimap.column_runs(2).is_empty());
+
+        // No more buckets than cells
+        assert_eq!(Minimap::new(&[&cells0[..2]], 4).buckets.len(), 2);
+        assert_eq!(Minimap::new(&[], 4), Minimap::default());
+        assert!(Minimap::new(&[&cells0[..2], &cells1[..2]], 4)
+            .runs()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_colors() {
+        let density = |same, diff, blank| Density { same, diff, blank };
+        assert_eq!(density(0, 0, 0).color(), None);
+        assert_eq!(density(5, 0, 0).color(), Some(SAME_COLOR));
+        assert_eq!(density(0, 5, 0).color(), Some(DIFF_COLOR));
+        assert_eq!(
+            density(0, 1, 3).color(),
+            Some(SAME_COLOR.lerp_to_gamma(BLANK_COLOR, 0.75))
+        );
+        // A single difference among many is as visible as half.
+        assert_eq!(density(1000, 1, 0).color(), density(1, 1, 0).color());
+    }
+}
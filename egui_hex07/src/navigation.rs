@@ -0,0 +1,61 @@
+//! Moving from one run of cells, such as a difference or a search hit, to
+//! the next.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Sorted, disjoint ranges of cell indices, shared between frames.
+pub type Runs = Arc<[Range<usize>]>;
+
+/// Index in `runs` of the first run starting after cell `index`, or of the
+/// first run if there is no index.
+pub fn next_run(runs: &[Range<usize>], index: Option<usize>) -> Option<usize> {
+    let next = match index {
+        Some(index) => runs.partition_point(|run| run.start <= index),
+        None => 0,
+    };
+    (next < runs.len()).then_some(next)
+}
+
+/// Index in `runs` of the last run ending before cell `index`, or of the last
+/// run if there is no index.
+pub fn previous_run(runs: &[Range<usize>], index: Option<usize>) -> Option<usize> {
+    match index {
+        Some(index) => runs.partition_point(|run| run.end <= index).checked_sub(1),
+        None => runs.len().checked_sub(1),
+    }
+}
+
+/// Index in `runs` of the run containing cell `index`.
+pub fn run_at(runs: &[Range<usize>], index: usize) -> Option<usize> {
+    let run = runs.partition_point(|run| run.end <= index);
+    runs.get(run)
+        .is_some_and(|run| run.contains(&index))
+        .then_some(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_and_previous() {
+        let runs = [2..4, 8..9, 12..16];
+        assert_eq!(next_run(&runs, None), Some(0));
+        assert_eq!(next_run(&runs, Some(0)), Some(0));
+        assert_eq!(next_run(&runs, Some(2)), Some(1), "from the start of a run");
+        assert_eq!(next_run(&runs, Some(3)), Some(1));
+        assert_eq!(next_run(&runs, Some(12)), None);
+
+        assert_eq!(previous_run(&runs, None), Some(2));
+        assert_eq!(previous_run(&runs, Some(12)), Some(1));
+        assert_eq!(previous_run(&runs, Some(9)), Some(1));
+        assert_eq!(previous_run(&runs, Some(8)), Some(0));
+        assert_eq!(previous_run(&runs, Some(3)), None);
+
+        assert_eq!(run_at(&runs, 8), Some(1));
+        assert_eq!(run_at(&runs, 9), None);
+        assert_eq!(next_run(&[], None), None);
+        assert_eq!(previous_run(&[], None), None);
+    }
+}
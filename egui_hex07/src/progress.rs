@@ -0,0 +1,130 @@
+use arb_comp06::bpe::MergeInfo;
+use egui::{ProgressBar, Ui};
+use std::time::Duration;
+use web_time::Instant;
+
+/// Stage of a diff job, as reported by its worker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Training,
+    Encoding,
+    Matching,
+    Rendering,
+    Done,
+    Cancelled,
+}
+
+/// Message sent from a diff job worker to the UI.
+#[derive(Debug, Clone)]
+pub enum ProgressMessage {
+    Phase(Phase),
+    Merge(MergeInfo),
+    /// Progress within the current phase.
+    Step {
+        done: usize,
+        total: usize,
+    },
+}
+
+/// Progress of the current (or most recent) diff job.
+pub struct Progress {
+    phase: Option<Phase>,
+    started: Instant,
+    finished: Option<Instant>,
+    merges: Vec<MergeInfo>,
+    step: Option<(usize, usize)>,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self {
+            phase: None,
+            started: Instant::now(),
+            finished: None,
+            merges: vec![],
+            step: None,
+        }
+    }
+
+    /// Resets progress for a newly started job.
+    pub fn start(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn update(&mut self, message: ProgressMessage) {
+        match message {
+            ProgressMessage::Phase(phase) => {
+                if matches!(phase, Phase::Done | Phase::Cancelled) {
+                    self.finished = Some(Instant::now());
+                }
+                self.phase = Some(phase);
+                self.step = None;
+            }
+            ProgressMessage::Merge(info) => self.merges.push(info),
+            ProgressMessage::Step { done, total } => self.step = Some((done, total)),
+        }
+    }
+
+    /// Every merge made so far by the current job, in order.
+    pub fn merges(&self) -> &[MergeInfo] {
+        &self.merges
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.finished.is_none()
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(Instant::now) - self.started
+    }
+
+    pub fn show(&self, ui: &mut Ui) {
+        let Some(phase) = self.phase else {
+            ui.label("idle");
+            return;
+        };
+
+        let latest_merge = self.merges.last();
+
+        let bar = match (phase, latest_merge) {
+            (Phase::Training, Some(info)) => {
+                let merges = self.merges.len();
+                let estimated_total = merges + info.remaining_pairs;
+                ProgressBar::new(merges as f32 / estimated_total.max(1) as f32)
+                    .text(format!("merge {merges} / ~{estimated_total}"))
+            }
+            (Phase::Done, _) => ProgressBar::new(1.0).text("done"),
+            (Phase::Cancelled, _) => ProgressBar::new(0.0).text("cancelled"),
+            (phase, _) => {
+                let name = format!("{phase:?}").to_lowercase();
+                match self.step {
+                    Some((done, total)) => {
+                        let fraction = done as f32 / total.max(1) as f32;
+                        ProgressBar::new(fraction).text(format!("{name} {:.0}%", fraction * 100.0))
+                    }
+                    None => ProgressBar::new(0.0).animate(true).text(name),
+                }
+            }
+        };
+        ui.add(bar);
+
+        ui.horizontal(|ui| {
+            ui.label(format!("phase: {phase:?}"));
+            ui.separator();
+            if let Some(info) = latest_merge {
+                ui.label(format!("vocabulary: {}", info.vocabulary_size));
+                ui.separator();
+                ui.label(format!("latest pair frequency: {}", info.pair_count));
+                ui.separator();
+                ui.label(format!("est. remaining merges: {}", info.remaining_pairs));
+                ui.separator();
+            }
+            ui.label(format!("elapsed: {:.1}s", self.elapsed().as_secs_f32()));
+        });
+
+        if self.is_running() {
+            // keep the elapsed time ticking between worker messages
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+    }
+}
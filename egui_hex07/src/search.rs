@@ -0,0 +1,375 @@
+//! Finding byte patterns in the aligned files, on a worker thread.
+
+use crate::diff::HexCell;
+use crate::jobs::{Job, JobRunner, Task};
+use crate::snapshots::DiffResults;
+use regex::bytes::{Regex, RegexBuilder};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// How the text of a query is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchKind {
+    /// Hex byte values, like "de ad BE EF".
+    Hex,
+    /// The UTF-8 bytes of the text, which for ASCII are the characters.
+    Text,
+    /// A regular expression over bytes: `.` matches any byte, and `\xFF` the
+    /// byte 0xFF.
+    Regex,
+}
+
+/// A search, as entered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub text: String,
+    pub kind: SearchKind,
+}
+
+/// Bytes written as hex values. Whitespace between them is optional.
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.len() % 2 != 0 {
+        return Err(format!("{text:?} has an odd number of hex digits"));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = String::from_iter(pair);
+            u8::from_str_radix(&pair, 16).map_err(|_| format!("{pair:?} is not a hex byte"))
+        })
+        .collect()
+}
+
+/// An offset written in hex like the address columns, with or without "0x".
+pub fn parse_offset(text: &str) -> Result<usize, String> {
+    let text = text.trim();
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    usize::from_str_radix(digits, 16).map_err(|_| format!("{text:?} is not a hex offset"))
+}
+
+impl Query {
+    /// The query as a regex over bytes.
+    pub fn regex(&self) -> Result<Regex, String> {
+        if self.text.is_empty() {
+            return Err("nothing to search for".to_string());
+        }
+        let pattern = match self.kind {
+            SearchKind::Hex => escape(&parse_hex(&self.text)?),
+            SearchKind::Text => escape(self.text.as_bytes()),
+            SearchKind::Regex => self.text.clone(),
+        };
+        RegexBuilder::new(&pattern)
+            .unicode(false)
+            .build()
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Byte ranges of the non-empty, non-overlapping matches of `regex` in `bytes`.
+pub fn find(regex: &Regex, bytes: &[u8]) -> Vec<Range<usize>> {
+    regex
+        .find_iter(bytes)
+        .map(|found| found.range())
+        .filter(|range| !range.is_empty())
+        .collect()
+}
+
+/// A regex matching exactly `bytes`.
+fn escape(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!(r"\x{byte:02X}")).collect()
+}
+
+/// Cell ranges in the aligned `cells` of a file of the sorted byte ranges
+/// `found` in it. A range spans any blank cells between its bytes.
+pub fn cell_ranges(cells: &[HexCell], found: &[Range<usize>]) -> Vec<Range<usize>> {
+    // Cell index of every byte offset that starts or ends a range, in order.
+    let mut offsets = found.iter().flat_map(|range| [range.start, range.end - 1]);
+    let mut wanted = offsets.next();
+    let mut indices = vec![];
+    let bytes = cells
+        .iter()
+        .enumerate()
+        .filter(|(_, cell)| **cell != HexCell::Blank);
+    for (offset, (index, _)) in bytes.enumerate() {
+        while wanted == Some(offset) {
+            indices.push(index);
+            wanted = offsets.next();
+        }
+    }
+    indices
+        .chunks_exact(2)
+        .map(|pair| pair[0]..pair[1] + 1)
+        .collect()
+}
+
+/// Where a query was found in each shown file.
+#[derive(Debug, Default, PartialEq)]
+pub struct Hits {
+    /// Cell ranges of the matches in each file.
+    pub columns: Vec<Vec<Range<usize>>>,
+    /// Cell ranges of the matches in any file, overlapping ones joined.
+    pub all: Vec<Range<usize>>,
+    /// Column of the file each range of `all` starts with a match in.
+    pub all_columns: Vec<usize>,
+}
+
+impl Hits {
+    /// Finds `regex` in `files`, each a file's bytes and its aligned cells.
+    /// None if `job` is cancelled first.
+    pub fn new(files: &[(&[u8], &[HexCell])], regex: &Regex, job: &Job) -> Option<Self> {
+        let mut columns = Vec::with_capacity(files.len());
+        for (bytes, cells) in files {
+            if job.is_cancelled() {
+                return None;
+            }
+            columns.push(cell_ranges(cells, &find(regex, bytes)));
+        }
+
+        let mut ranges: Vec<_> = columns
+            .iter()
+            .enumerate()
+            .flat_map(|(column, ranges)| ranges.iter().map(move |range| (range.clone(), column)))
+            .collect();
+        ranges.sort_by_key(|(range, column)| (range.start, *column));
+        let mut all: Vec<Range<usize>> = vec![];
+        let mut all_columns = vec![];
+        for (range, column) in ranges {
+            match all.last_mut() {
+                Some(last) if range.start < last.end => last.end = last.end.max(range.end),
+                _ => {
+                    all.push(range);
+                    all_columns.push(column);
+                }
+            }
+        }
+        Some(Hits {
+            columns,
+            all,
+            all_columns,
+        })
+    }
+}
+
+/// What a search looks in: the files of one generation of results, by their
+/// index in `DiffResults::files`.
+#[derive(Debug, Clone, PartialEq)]
+struct Searched {
+    query: Query,
+    generation: u64,
+    files: Vec<usize>,
+}
+
+/// A search for a worker to run.
+struct Request {
+    regex: Regex,
+    results: Arc<DiffResults>,
+    files: Vec<usize>,
+}
+
+/// Finds `request.regex` in the bytes of its files, and sends the hits in
+/// their cells if `job` is still current.
+fn run(request: Request, job: &Job, send: &dyn Fn(Hits)) {
+    let results = &request.results;
+    let cells = [&results.diffs0, &results.diffs1, &results.diffs_base];
+    let files: Vec<(&[u8], &[HexCell])> = request
+        .files
+        .iter()
+        .map(|&file| {
+            let bytes = results.files[file]
+                .as_deref()
+                .map_or(&[][..], |input| input);
+            (bytes, &cells[file][..])
+        })
+        .collect();
+    if let Some(hits) = Hits::new(&files, &request.regex, job) {
+        if job.is_current() {
+            send(hits);
+        }
+    }
+}
+
+/// Searches the shown files, one job at a time, so a large file doesn't
+/// hold up the UI.
+///
+/// Call `poll` once per frame to start a queued job and collect its hits.
+pub struct SearchJobs {
+    runner: JobRunner<Request, Hits>,
+    /// What the latest request searches.
+    requested: Option<Searched>,
+    /// What the running or last job searched.
+    started: Option<Searched>,
+    hits: Option<(Searched, Arc<Hits>)>,
+}
+
+impl SearchJobs {
+    /// Runs jobs on the rayon thread pool.
+    pub fn new(request_repaint: impl Fn() + Send + Sync + 'static) -> Self {
+        Self::with_spawner(request_repaint, rayon::spawn)
+    }
+
+    /// Like `new`, but runs jobs with `spawn`.
+    pub fn with_spawner(
+        request_repaint: impl Fn() + Send + Sync + 'static,
+        spawn: impl Fn(Task) + 'static,
+    ) -> Self {
+        Self {
+            runner: JobRunner::new(run, request_repaint, spawn),
+            requested: None,
+            started: None,
+            hits: None,
+        }
+    }
+
+    /// Hits of `query`, compiled to `regex`, in `files` of `results`, by
+    /// their index in `DiffResults::files`. Until they are found, requests a
+    /// search for them and returns None.
+    pub fn hits(
+        &mut self,
+        query: &Query,
+        regex: &Regex,
+        results: &Arc<DiffResults>,
+        files: Vec<usize>,
+    ) -> Option<Arc<Hits>> {
+        let searched = Searched {
+            query: query.clone(),
+            generation: results.generation,
+            files,
+        };
+        if self.requested.as_ref() != Some(&searched) {
+            self.runner.request(Request {
+                regex: regex.clone(),
+                results: results.clone(),
+                files: searched.files.clone(),
+            });
+            self.requested = Some(searched.clone());
+            self.poll();
+        }
+        match &self.hits {
+            Some((found, hits)) if *found == searched => Some(hits.clone()),
+            _ => None,
+        }
+    }
+
+    /// Cancels any running search and drops the hits.
+    pub fn clear(&mut self) {
+        self.runner.cancel();
+        self.requested = None;
+        self.hits = None;
+    }
+
+    /// Starts the queued job if no job is running, and collects its hits.
+    pub fn poll(&mut self) {
+        if self.runner.start_queued().is_some() {
+            self.started = self.requested.clone();
+        }
+        for hits in self.runner.messages() {
+            if let Some(searched) = &self.started {
+                self.hits = Some((searched.clone(), Arc::new(hits)));
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.runner.is_running()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::Provenance;
+    use crate::jobs::JobManager;
+
+    fn same(value: u8) -> HexCell {
+        HexCell::Same {
+            value,
+            source_id: 0,
+            provenance: Provenance::BYTE,
+        }
+    }
+
+    fn one(range: Range<usize>) -> Vec<Range<usize>> {
+        vec![range]
+    }
+
+    fn query(text: &str, kind: SearchKind) -> Query {
+        Query {
+            text: text.to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_hex("de ad BE EF"), Ok(vec![0xDE, 0xAD, 0xBE, 0xEF]));
+        assert_eq!(parse_hex("00ff"), Ok(vec![0x00, 0xFF]));
+        assert!(parse_hex("abc").is_err());
+        assert!(parse_hex("zz").is_err());
+
+        assert_eq!(parse_offset("0x1F"), Ok(0x1F));
+        assert_eq!(parse_offset(" 0000001f "), Ok(0x1F));
+        assert!(parse_offset("").is_err());
+        assert!(parse_offset("g").is_err());
+    }
+
+    #[test]
+    fn test_find() {
+        let bytes = b"ab\xFFab\x00\xFF\xFFa";
+        let find = |text, kind| {
+            query(text, kind)
+                .regex()
+                .map(|regex| super::find(&regex, bytes))
+        };
+        assert_eq!(find("ff", SearchKind::Hex), Ok(vec![2..3, 6..7, 7..8]));
+        assert_eq!(find("ab", SearchKind::Text), Ok(vec![0..2, 3..5]));
+        assert_eq!(find("a.", SearchKind::Text), Ok(vec![]), "not a regex");
+        assert_eq!(find(r"\xFF+", SearchKind::Regex), Ok(vec![2..3, 6..8]));
+        assert_eq!(find("b.a", SearchKind::Regex), Ok(one(1..4)));
+        assert_eq!(find("x*", SearchKind::Regex), Ok(vec![]), "empty matches");
+        assert!(find("(", SearchKind::Regex).is_err());
+        assert!(find("", SearchKind::Text).is_err());
+
+        let text = "é".to_string();
+        let regex = query(&text, SearchKind::Text).regex().unwrap();
+        assert_eq!(super::find(&regex, text.as_bytes()), one(0..2));
+    }
+
+    #[test]
+    fn test_hits() {
+        let cells0 = [same(1), same(2), HexCell::Blank, same(3), same(1), same(2)];
+        let cells1 = [same(1), same(2), same(1), same(2), HexCell::Blank, same(3)];
+        assert_eq!(cell_ranges(&cells0, &[1..3, 4..5]), [1..4, 5..6]);
+        assert!(
+            cell_ranges(&cells0, &one(4..6)).is_empty(),
+            "past the cells"
+        );
+
+        let mut jobs = JobManager::new();
+        jobs.request();
+        let job = jobs.poll().unwrap();
+        let files: [(&[u8], &[HexCell]); 2] =
+            [(&[1, 2, 3, 1, 2], &cells0), (&[1, 2, 1, 2, 3], &cells1)];
+        let hits = |text| {
+            let regex = query(text, SearchKind::Hex).regex().unwrap();
+            Hits::new(&files, &regex, &job).unwrap()
+        };
+
+        let hits0102 = hits("0102");
+        assert_eq!(hits0102.columns, [vec![0..2, 4..6], vec![0..2, 2..4]]);
+        assert_eq!(hits0102.all, [0..2, 2..4, 4..6], "touching hits stay apart");
+        assert_eq!(hits0102.all_columns, [0, 1, 0]);
+
+        let hits = hits("02 03");
+        assert_eq!(hits.columns, [one(1..4), one(3..6)]);
+        assert_eq!(hits.all, one(1..6));
+        assert_eq!(hits.all_columns, [0]);
+
+        jobs.cancel();
+        let regex = query("01", SearchKind::Hex).regex().unwrap();
+        assert_eq!(Hits::new(&files, &regex, &job), None);
+    }
+}
@@ -0,0 +1,66 @@
+//! Immutable snapshots shared between the UI and diff jobs.
+//!
+//! Neither side holds a lock while working: a job reads the `Inputs` it was
+//! started with, and publishes a complete `DiffResults` by swapping a pointer.
+
+use crate::diff::HexCell;
+use crate::hex_app::DiffMethod;
+use crate::token_inspector::TokenInfo;
+use arb_comp06::{input::Input, similarity::Similarity};
+use arc_swap::ArcSwap;
+use std::sync::Arc;
+
+/// Everything a diff job needs, captured when the job starts.
+#[derive(Debug, Clone)]
+pub struct Inputs {
+    /// Id of the job these inputs were captured for.
+    pub generation: u64,
+    /// The common base of a three-way diff of pattern 0 and pattern 1.
+    pub base: Option<Arc<Input>>,
+    pub pattern0: Option<Arc<Input>>,
+    pub pattern1: Option<Arc<Input>>,
+    pub diff_method: DiffMethod,
+}
+
+/// Output of a completed diff job.
+#[derive(Debug, Default)]
+pub struct DiffResults {
+    /// `Inputs::generation` of the inputs these results were computed from.
+    pub generation: u64,
+    /// Cells of the base of a three-way diff, empty for a two-way diff.
+    pub diffs_base: Vec<HexCell>,
+    pub diffs0: Vec<HexCell>,
+    pub diffs1: Vec<HexCell>,
+    /// The bytes of file 0, file 1 and the base that the cells are of.
+    pub files: [Option<Arc<Input>>; 3],
+    /// Cell `i` of one file isn't next to cell `i` of the others, because
+    /// each file is shown in its own tokens without padding.
+    pub unaligned: bool,
+    pub vocabulary: Vec<TokenInfo>,
+    /// Similarity of the patterns of a two-way BPE diff.
+    pub similarity: Option<Similarity>,
+}
+
+/// The most recently published `DiffResults`.
+#[derive(Default)]
+pub struct Published(ArcSwap<DiffResults>);
+
+impl Published {
+    pub fn load(&self) -> Arc<DiffResults> {
+        self.0.load_full()
+    }
+
+    /// Publishes `results` unless results of a later generation are already
+    /// published. Returns true if `results` were published.
+    pub fn publish(&self, results: DiffResults) -> bool {
+        let results = Arc::new(results);
+        let previous = self.0.rcu(|current| {
+            if current.generation > results.generation {
+                current.clone()
+            } else {
+                results.clone()
+            }
+        });
+        previous.generation <= results.generation
+    }
+}
@@ -0,0 +1,148 @@
+use arb_comp06::{
+    bpe::Bpe,
+    token::{Token, TokenId},
+};
+use egui::{RichText, Ui};
+use egui_extras::{Column, TableBuilder};
+
+/// A token learned by `Bpe`, with its occurrence counts in each encoded file.
+#[derive(Debug, Clone)]
+pub struct TokenInfo {
+    pub id: TokenId,
+    pub token: Token,
+    pub bytes: Vec<u8>,
+    /// Occurrences in file 0, file 1 and the base.
+    pub counts: [usize; 3],
+    /// Depth of the token's merge tree, 0 for a byte.
+    pub depth: u32,
+}
+
+/// Collects every token in `bpe`'s vocabulary, indexed by `TokenId`, counted
+/// in the `encoded` file 0, file 1 and base (empty for a two-way diff).
+pub fn vocabulary(bpe: &Bpe, encoded: [&[TokenId]; 3]) -> Vec<TokenInfo> {
+    let depths = bpe.depths();
+    let mut vocabulary = bpe
+        .ids_to_tokens()
+        .iter()
+        .map(|(&id, &token)| TokenInfo {
+            id,
+            token,
+            bytes: bpe.decode(vec![id]),
+            counts: [0; 3],
+            depth: depths[id.index()],
+        })
+        .collect::<Vec<_>>();
+
+    for (i, encoded) in encoded.into_iter().enumerate() {
+        for id in encoded {
+            vocabulary[id.index()].counts[i] += 1;
+        }
+    }
+
+    vocabulary
+}
+
+fn merge_text(token: Token) -> String {
+    match token {
+        Token::Byte(b) => format!("byte {b:02X}"),
+        Token::Merge(id0, id1) => format!("{} + {}", id0.0, id1.0),
+    }
+}
+
+const MAX_SHOWN_BYTES: usize = 16;
+
+pub fn hex_text(bytes: &[u8]) -> String {
+    let mut s = bytes
+        .iter()
+        .take(MAX_SHOWN_BYTES)
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if bytes.len() > MAX_SHOWN_BYTES {
+        s.push_str(" …");
+    }
+    s
+}
+
+pub fn ascii_text(bytes: &[u8]) -> String {
+    let mut s = bytes
+        .iter()
+        .take(MAX_SHOWN_BYTES)
+        .map(|&b| {
+            char::from_u32(b as u32)
+                .filter(|c| c.is_ascii() && !c.is_control())
+                .unwrap_or('.')
+        })
+        .collect::<String>();
+    if bytes.len() > MAX_SHOWN_BYTES {
+        s.push('…');
+    }
+    s
+}
+
+/// Shows a table of `vocabulary`. Clicking a token id toggles it as `selected`.
+pub fn show(ui: &mut Ui, vocabulary: &[TokenInfo], selected: &mut Option<TokenId>) {
+    if vocabulary.is_empty() {
+        ui.label("no vocabulary (run a BPE diff method)");
+        return;
+    }
+
+    ui.label(format!("{} tokens", vocabulary.len()));
+    // The base is only counted in a three-way diff.
+    let three_way = vocabulary.iter().any(|info| info.counts[2] > 0);
+    let counted = if three_way { 3 } else { 2 };
+    let titles = [
+        "id", "merge", "hex", "ascii", "len", "file 0", "file 1", "base",
+    ];
+    let titles = &titles[..5 + counted];
+
+    TableBuilder::new(ui)
+        .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+        .striped(true)
+        .column(Column::auto())
+        .column(Column::auto())
+        .column(Column::auto().resizable(true))
+        .column(Column::auto().resizable(true))
+        // len, and all counts but the last
+        .columns(Column::auto(), counted)
+        .column(Column::remainder())
+        .header(20.0, |mut header| {
+            for title in titles {
+                header.col(|ui| {
+                    ui.strong(*title);
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(18.0, vocabulary.len(), |mut row| {
+                let info = &vocabulary[row.index()];
+
+                row.col(|ui| {
+                    let is_selected = *selected == Some(info.id);
+                    if ui
+                        .selectable_label(is_selected, RichText::new(info.id.0.to_string()))
+                        .clicked()
+                    {
+                        *selected = if is_selected { None } else { Some(info.id) };
+                    }
+                });
+                row.col(|ui| {
+                    ui.monospace(merge_text(info.token));
+                });
+                row.col(|ui| {
+                    ui.monospace(hex_text(&info.bytes));
+                });
+                row.col(|ui| {
+                    ui.monospace(ascii_text(&info.bytes));
+                });
+                row.col(|ui| {
+                    ui.label(info.bytes.len().to_string());
+                });
+                for count in &info.counts[..counted] {
+                    row.col(|ui| {
+                        ui.label(count.to_string());
+                    });
+                }
+            });
+        });
+}
@@ -0,0 +1,153 @@
+//! Watching input files for changes on disk (native only).
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// How long a changed file must stay unchanged before it is reported, so a
+/// file that is still being written isn't read half-way.
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+/// Reports changes to a set of files.
+///
+/// Files are watched through their directories, so a file replaced by a
+/// rename (as many build tools do) is still seen as changed.
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    rx: mpsc::Receiver<notify::Result<notify::Event>>,
+    paths: Vec<PathBuf>,
+    watched_dirs: Vec<PathBuf>,
+    /// Changed paths, and when each last changed.
+    changed: HashMap<PathBuf, Instant>,
+}
+
+impl FileWatcher {
+    /// `on_event` is called from the watcher thread on every file system event.
+    pub fn new(on_event: impl Fn() + Send + 'static) -> notify::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            tx.send(event).ok();
+            on_event();
+        })?;
+        Ok(Self {
+            watcher,
+            rx,
+            paths: vec![],
+            watched_dirs: vec![],
+            changed: HashMap::new(),
+        })
+    }
+
+    /// Watches `paths` instead of the previously watched paths. Paths should
+    /// be canonical, to match the paths of events.
+    pub fn set_paths(&mut self, paths: Vec<PathBuf>) -> notify::Result<()> {
+        if paths == self.paths {
+            return Ok(());
+        }
+
+        let mut dirs: Vec<PathBuf> = paths
+            .iter()
+            .filter_map(|path| path.parent())
+            .map(Path::to_path_buf)
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+
+        let previous_dirs = std::mem::take(&mut self.watched_dirs);
+        for dir in previous_dirs.iter().filter(|dir| !dirs.contains(dir)) {
+            self.watcher.unwatch(dir).ok();
+        }
+
+        let mut result = Ok(());
+        for dir in dirs {
+            if previous_dirs.contains(&dir) {
+                self.watched_dirs.push(dir);
+            } else {
+                match self.watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                    Ok(()) => self.watched_dirs.push(dir),
+                    Err(e) => result = Err(e),
+                }
+            }
+        }
+
+        self.changed.retain(|path, _| paths.contains(path));
+        self.paths = paths;
+        result
+    }
+
+    /// Returns the watched paths that changed and have since settled.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        for event in self.rx.try_iter() {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    for path in event.paths {
+                        if self.paths.contains(&path) {
+                            self.changed.insert(path, now);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("file watcher error: {e}"),
+            }
+        }
+
+        let settled: Vec<PathBuf> = self
+            .changed
+            .iter()
+            .filter(|(_, changed)| now - **changed >= SETTLE_TIME)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &settled {
+            self.changed.remove(path);
+        }
+        settled
+    }
+
+    /// Time until the next pending change settles, if any.
+    pub fn next_poll(&self) -> Option<Duration> {
+        self.changed
+            .values()
+            .map(|changed| SETTLE_TIME.saturating_sub(changed.elapsed()))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_watcher() {
+        let dir = std::env::temp_dir().join(format!("egui_hex07_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        let watched = dir.join("watched.bin");
+        let other = dir.join("other.bin");
+        std::fs::write(&watched, b"0").unwrap();
+
+        let mut watcher = FileWatcher::new(|| {}).unwrap();
+        watcher.set_paths(vec![watched.clone()]).unwrap();
+        assert_eq!(watcher.poll(), Vec::<PathBuf>::new());
+        assert_eq!(watcher.next_poll(), None);
+
+        std::fs::write(&other, b"1").unwrap();
+        std::fs::write(&watched, b"1").unwrap();
+
+        // Changes are reported once they have settled.
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let changed = loop {
+            let changed = watcher.poll();
+            if !changed.is_empty() || Instant::now() > deadline {
+                break changed;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        };
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(changed, [watched]);
+        assert_eq!(watcher.next_poll(), None);
+    }
+}